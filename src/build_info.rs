@@ -0,0 +1,218 @@
+use std::path::PathBuf;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::cargo::CargoToml;
+use crate::git::{
+    git_path, head_commit_hash, head_ref, last_commit_date, source_date_epoch, unstaged_changes,
+    InvalidSourceDateEpoch, LastCommitError,
+};
+use crate::package_utils::buildhost;
+
+pub use crate::git::SOURCE_DATE_EPOCH;
+const UNKNOWN: &str = "unknown";
+
+#[derive(Debug, thiserror::Error)]
+pub enum BuildInfoError {
+    #[error(transparent)]
+    XShellError(#[from] xshell::Error),
+
+    #[error(transparent)]
+    CommitDateError(#[from] LastCommitError),
+
+    #[error(transparent)]
+    InvalidSourceDateEpoch(#[from] InvalidSourceDateEpoch),
+}
+
+/// Emits `cargo:rustc-env=` variables describing the build. Meant to be used from `build.rs`
+///
+/// ```ignore
+/// fn main() {
+///     xtask_toolkit::build_info::BuildInfo::new().emit().unwrap();
+/// }
+/// ```
+///
+/// Variables (with the default `BUILD_` prefix): `BUILD_GIT_HASH`, `BUILD_GIT_SHORT_HASH`,
+/// `BUILD_GIT_DIRTY`, `BUILD_COMMIT_DATE`, `BUILD_DATE`, `BUILD_HOST`, `BUILD_TARGET`,
+/// `BUILD_PROFILE`, `BUILD_VERSIONED_NAME` and `BUILD_LONG_VERSION`
+#[derive(Debug, Clone)]
+pub struct BuildInfo {
+    prefix: String,
+    cargo_toml: Option<CargoToml>,
+    allow_missing_git: bool,
+    sources: Vec<PathBuf>,
+}
+
+impl Default for BuildInfo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BuildInfo {
+    pub fn new() -> Self {
+        let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from);
+        let cargo_toml = manifest_dir
+            .as_ref()
+            .and_then(|dir| CargoToml::find_first(dir, &[]));
+
+        // watching anything disables cargo's default of rerunning on any package change, so the
+        // sources are watched too for `GIT_DIRTY` to follow unstaged edits
+        let sources = manifest_dir
+            .map(|dir| {
+                ["Cargo.toml", "build.rs", "src"]
+                    .map(|x| dir.join(x))
+                    .to_vec()
+            })
+            .unwrap_or_default();
+
+        Self {
+            prefix: "BUILD_".to_string(),
+            cargo_toml,
+            allow_missing_git: false,
+            sources,
+        }
+    }
+
+    /// Prefix of the emitted variables. Default value is `BUILD_`
+    pub fn with_prefix<S>(&mut self, prefix: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Use a different Cargo.toml than the one from `CARGO_MANIFEST_DIR`
+    pub fn with_cargo_toml(&mut self, cargo_toml: CargoToml) -> &mut Self {
+        self.cargo_toml = Some(cargo_toml);
+        self
+    }
+
+    /// Rerun the build script when the file or directory changes, in addition to `Cargo.toml`,
+    /// `build.rs` and `src` of the package. Needed for other sources affecting `GIT_DIRTY`, e.g.
+    /// the other crates of a workspace
+    pub fn with_watched_path<P>(&mut self, path: P) -> &mut Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.sources.push(path.into());
+        self
+    }
+
+    /// Emit `unknown` git values instead of failing (e.g. when building from a crates.io tarball)
+    pub fn allow_missing_git(&mut self) -> &mut Self {
+        self.allow_missing_git = true;
+        self
+    }
+
+    /// Build date. `SOURCE_DATE_EPOCH` takes precedence over the current time
+    pub fn build_date() -> Result<DateTime<Utc>, BuildInfoError> {
+        Ok(source_date_epoch()?.unwrap_or_else(Utc::now))
+    }
+
+    fn env(&self, key: &str, value: &str) -> String {
+        format!("cargo:rustc-env={}{}={}", self.prefix, key, value)
+    }
+
+    /// Lines that `emit` prints to stdout
+    pub fn instructions(&self) -> Result<Vec<String>, BuildInfoError> {
+        let git = match GitInfo::read() {
+            Ok(git) => git,
+            Err(_) if self.allow_missing_git => GitInfo::unknown(),
+            Err(error) => return Err(error),
+        };
+
+        let mut result = vec![format!("cargo:rerun-if-env-changed={SOURCE_DATE_EPOCH}")];
+        result.extend(
+            git.watched
+                .iter()
+                .chain(self.sources.iter().filter(|path| path.exists()))
+                .map(|path| format!("cargo:rerun-if-changed={}", path.display())),
+        );
+
+        let build_date = Self::build_date()?.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let host = buildhost();
+        let target = std::env::var("TARGET").unwrap_or_else(|_| UNKNOWN.to_string());
+        let profile = std::env::var("PROFILE").unwrap_or_else(|_| UNKNOWN.to_string());
+
+        result.push(self.env("GIT_HASH", &git.hash));
+        result.push(self.env("GIT_SHORT_HASH", &git.short_hash));
+        result.push(self.env("GIT_DIRTY", &git.dirty.to_string()));
+        result.push(self.env("COMMIT_DATE", &git.commit_date));
+        result.push(self.env("DATE", &build_date));
+        result.push(self.env("HOST", &host));
+        result.push(self.env("TARGET", &target));
+        result.push(self.env("PROFILE", &profile));
+
+        if let Some(versioned_name) = self.cargo_toml.as_ref().and_then(|x| x.versioned_name()) {
+            result.push(self.env("VERSIONED_NAME", &versioned_name));
+        }
+
+        let version = self.cargo_toml.as_ref().and_then(|x| x.version());
+        let long_version = format!(
+            "{} ({}{} {}) built on {} for {}",
+            version.as_deref().unwrap_or(UNKNOWN),
+            git.short_hash,
+            if git.dirty { "-dirty" } else { "" },
+            git.commit_date,
+            host,
+            target,
+        );
+        result.push(self.env("LONG_VERSION", &long_version));
+
+        Ok(result)
+    }
+
+    /// Print all the `cargo:` instructions
+    pub fn emit(&self) -> Result<(), BuildInfoError> {
+        for line in self.instructions()? {
+            println!("{line}");
+        }
+        Ok(())
+    }
+}
+
+struct GitInfo {
+    hash: String,
+    short_hash: String,
+    dirty: bool,
+    commit_date: String,
+    watched: Vec<PathBuf>,
+}
+
+impl GitInfo {
+    fn read() -> Result<Self, BuildInfoError> {
+        let hash = head_commit_hash()?;
+        let short_hash = hash.chars().take(7).collect();
+
+        // HEAD changes on checkout, the ref on commit, the index on staging
+        let mut watched = vec![
+            git_path("HEAD")?,
+            git_path("packed-refs")?,
+            git_path("index")?,
+        ];
+        if let Some(head_ref) = head_ref()? {
+            watched.push(git_path(&head_ref)?);
+        }
+        watched.retain(|path| path.exists());
+
+        Ok(Self {
+            hash,
+            short_hash,
+            dirty: unstaged_changes()?,
+            commit_date: last_commit_date()?.to_rfc3339_opts(SecondsFormat::Secs, true),
+            watched,
+        })
+    }
+
+    fn unknown() -> Self {
+        Self {
+            hash: UNKNOWN.to_string(),
+            short_hash: UNKNOWN.to_string(),
+            dirty: false,
+            commit_date: UNKNOWN.to_string(),
+            watched: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use sha2::Digest;
+
+#[derive(Debug, thiserror::Error)]
+#[error("Unknown hash algorithm: {0}")]
+pub struct UnknownAlgorithm(pub String);
+
+/// Hash algorithms supported by [`super::PathChecksum`]
+///
+/// MD5 and SHA-1 are broken and only meant for legacy mirrors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub enum HashAlgorithm {
+    #[default]
+    Sha256,
+    Sha512,
+    Blake3,
+    Md5,
+    Sha1,
+}
+
+impl HashAlgorithm {
+    pub const ALL: [HashAlgorithm; 5] = [
+        Self::Sha256,
+        Self::Sha512,
+        Self::Blake3,
+        Self::Md5,
+        Self::Sha1,
+    ];
+
+    /// Lowercase name, as used in `<name>sum` tools and checksum file extensions
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+            Self::Sha512 => "sha512",
+            Self::Blake3 => "blake3",
+            Self::Md5 => "md5",
+            Self::Sha1 => "sha1",
+        }
+    }
+
+    /// Upper case name, as used in the BSD checksum format (`SHA256 (file) = ...`)
+    pub fn bsd_name(&self) -> &'static str {
+        match self {
+            Self::Sha256 => "SHA256",
+            Self::Sha512 => "SHA512",
+            Self::Blake3 => "BLAKE3",
+            Self::Md5 => "MD5",
+            Self::Sha1 => "SHA1",
+        }
+    }
+
+    /// Length of the digest in bytes
+    pub fn output_size(&self) -> usize {
+        match self {
+            Self::Sha256 | Self::Blake3 => 32,
+            Self::Sha512 => 64,
+            Self::Md5 => 16,
+            Self::Sha1 => 20,
+        }
+    }
+
+    /// Guess the algorithm from the length of a hex digest. 64 characters are assumed to be
+    /// SHA-256 rather than BLAKE3
+    pub(crate) fn from_hex_length(length: usize) -> Option<Self> {
+        match length {
+            32 => Some(Self::Md5),
+            40 => Some(Self::Sha1),
+            64 => Some(Self::Sha256),
+            128 => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn hasher(&self) -> Hasher {
+        match self {
+            Self::Sha256 => Hasher::Sha256(sha2::Sha256::new()),
+            Self::Sha512 => Hasher::Sha512(sha2::Sha512::new()),
+            Self::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+            Self::Md5 => Hasher::Md5(md5::Md5::new()),
+            Self::Sha1 => Hasher::Sha1(sha1::Sha1::new()),
+        }
+    }
+
+    /// Digest of an in-memory buffer
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        let mut hasher = self.hasher();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+impl std::fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for HashAlgorithm {
+    type Err = UnknownAlgorithm;
+
+    /// Case insensitive, dashes are ignored (`SHA-256`, `sha256`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized = s.to_lowercase().replace('-', "");
+        Self::ALL
+            .into_iter()
+            .find(|x| x.name() == normalized)
+            .ok_or_else(|| UnknownAlgorithm(s.to_string()))
+    }
+}
+
+pub(crate) enum Hasher {
+    Sha256(sha2::Sha256),
+    Sha512(sha2::Sha512),
+    Blake3(Box<blake3::Hasher>),
+    Md5(md5::Md5),
+    Sha1(sha1::Sha1),
+}
+
+impl Hasher {
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Sha256(hasher) => hasher.update(data),
+            Self::Sha512(hasher) => hasher.update(data),
+            Self::Blake3(hasher) => {
+                hasher.update(data);
+            }
+            Self::Md5(hasher) => hasher.update(data),
+            Self::Sha1(hasher) => hasher.update(data),
+        }
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        match self {
+            Self::Sha256(hasher) => hasher.finalize().to_vec(),
+            Self::Sha512(hasher) => hasher.finalize().to_vec(),
+            Self::Blake3(hasher) => hasher.finalize().as_bytes().to_vec(),
+            Self::Md5(hasher) => hasher.finalize().to_vec(),
+            Self::Sha1(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
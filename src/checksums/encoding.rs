@@ -0,0 +1,260 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+
+use super::{Checksum, HashAlgorithm};
+
+/// Alphabet of the Nix base32 encoding (no `e`, `o`, `u` and `t`)
+const NIX_BASE32: &[u8; 32] = b"0123456789abcdfghijklmnpqrsvwxyz";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ChecksumParseError {
+    #[error(transparent)]
+    UnknownAlgorithm(#[from] super::UnknownAlgorithm),
+
+    #[error("Could not guess the hash algorithm of {0}")]
+    MissingAlgorithm(String),
+
+    #[error("Invalid digest encoding: {0}")]
+    InvalidEncoding(String),
+
+    #[error("{algorithm} digest must be {expected} bytes long, got {actual}")]
+    InvalidLength {
+        algorithm: HashAlgorithm,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) || !value.is_ascii() {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&value[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Length of the Nix base32 representation of `size` bytes
+fn nix_base32_length(size: usize) -> usize {
+    (size * 8).div_ceil(5)
+}
+
+/// Nix base32: little endian 5 bit groups, printed from the most significant one
+fn encode_nix_base32(bytes: &[u8]) -> String {
+    (0..nix_base32_length(bytes.len()))
+        .rev()
+        .map(|n| {
+            let bit = n * 5;
+            let (i, j) = (bit / 8, bit % 8);
+            let low = bytes[i] >> j;
+            let high = bytes.get(i + 1).map_or(0, |x| (*x as u16) << (8 - j)) as u8;
+            NIX_BASE32[((low | high) & 0x1f) as usize] as char
+        })
+        .collect()
+}
+
+fn decode_nix_base32(value: &str, size: usize) -> Option<Vec<u8>> {
+    if value.len() != nix_base32_length(size) {
+        return None;
+    }
+
+    let mut bytes = vec![0u8; size];
+    for (n, c) in value.bytes().rev().enumerate() {
+        let digit = NIX_BASE32.iter().position(|x| *x == c)? as u16;
+        let bit = n * 5;
+        let (i, j) = (bit / 8, bit % 8);
+        bytes[i] |= (digit << j) as u8;
+
+        let carry = (digit << j) >> 8;
+        match bytes.get_mut(i + 1) {
+            Some(byte) => *byte |= carry as u8,
+            None if carry != 0 => return None,
+            None => (),
+        }
+    }
+    Some(bytes)
+}
+
+impl Checksum {
+    /// Raw digest bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        decode_hex(&self.value).unwrap_or_default()
+    }
+
+    /// Subresource Integrity form, e.g. `sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=`.
+    /// Also the form of Nix `hash` and `narHash` attributes
+    pub fn to_sri(&self) -> String {
+        format!("{}-{}", self.algorithm, BASE64.encode(self.to_bytes()))
+    }
+
+    /// Nix base32 form without the algorithm, as printed by `nix-hash --to-base32`
+    pub fn to_nix_base32(&self) -> String {
+        encode_nix_base32(&self.to_bytes())
+    }
+
+    /// Nix base32 form with the algorithm, e.g. `sha256:0mdqa9w1p6cmli6976v4wi0sw9r4p5prkj7lzfd1877wk11c9c73`
+    pub fn to_nix(&self) -> String {
+        format!("{}:{}", self.algorithm, self.to_nix_base32())
+    }
+
+    /// Digest in any of the encodings Nix accepts for the algorithm: hex, Nix base32 or base64
+    pub fn decode(algorithm: HashAlgorithm, value: &str) -> Result<Self, ChecksumParseError> {
+        let size = algorithm.output_size();
+        let invalid = || ChecksumParseError::InvalidEncoding(value.to_string());
+
+        let bytes = if value.len() == size * 2 {
+            decode_hex(value).ok_or_else(invalid)?
+        } else if value.len() == nix_base32_length(size) {
+            decode_nix_base32(value, size).ok_or_else(invalid)?
+        } else {
+            BASE64.decode(value).map_err(|_| invalid())?
+        };
+
+        if bytes.len() != size {
+            return Err(ChecksumParseError::InvalidLength {
+                algorithm,
+                expected: size,
+                actual: bytes.len(),
+            });
+        }
+        Ok(Self::from_bytes(algorithm, &bytes))
+    }
+
+    /// Parse `<algorithm>-<base64>` (SRI), `<algorithm>:<digest>` (Nix) or a bare hex digest,
+    /// whose algorithm is guessed from the length
+    pub fn parse(value: &str) -> Result<Self, ChecksumParseError> {
+        let value = value.trim();
+
+        if let Some((algorithm, digest)) = value.split_once(':') {
+            return Self::decode(algorithm.parse()?, digest);
+        }
+        if let Some((algorithm, digest)) = value.split_once('-') {
+            if let Ok(algorithm) = algorithm.parse() {
+                return Self::decode(algorithm, digest);
+            }
+        }
+
+        let algorithm = HashAlgorithm::from_hex_length(value.len())
+            .ok_or_else(|| ChecksumParseError::MissingAlgorithm(value.to_string()))?;
+        Self::decode(algorithm, value)
+    }
+}
+
+impl std::str::FromStr for Checksum {
+    type Err = ChecksumParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::super::PathChecksum;
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    const EMPTY_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    const EMPTY_SHA256_NIX32: &str = "0mdqa9w1p6cmli6976v4wi0sw9r4p5prkj7lzfd1877wk11c9c73";
+    const EMPTY_SHA256_SRI: &str = "sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=";
+
+    fn empty_sha256() -> Checksum {
+        Checksum::new(HashAlgorithm::Sha256, EMPTY_SHA256)
+    }
+
+    #[test]
+    fn empty_sha256_encodings() {
+        let checksum = empty_sha256();
+        assert_eq!(checksum.to_nix_base32(), EMPTY_SHA256_NIX32);
+        assert_eq!(checksum.to_nix(), format!("sha256:{EMPTY_SHA256_NIX32}"));
+        assert_eq!(checksum.to_sri(), EMPTY_SHA256_SRI);
+    }
+
+    #[test]
+    fn parse_encodings() {
+        for value in [
+            EMPTY_SHA256.to_string(),
+            format!("sha256:{EMPTY_SHA256}"),
+            format!("sha256:{EMPTY_SHA256_NIX32}"),
+            format!("  {EMPTY_SHA256_SRI}\n"),
+        ] {
+            assert_eq!(Checksum::parse(&value).unwrap(), empty_sha256(), "{value}");
+        }
+    }
+
+    #[test]
+    fn parse_round_trip() {
+        for algorithm in HashAlgorithm::ALL {
+            let bytes = (0..algorithm.output_size() as u8).collect::<Vec<_>>();
+            let checksum = Checksum::from_bytes(algorithm, &bytes);
+
+            for value in [
+                checksum.to_sri(),
+                checksum.to_nix(),
+                checksum.get().to_string(),
+            ] {
+                let parsed: Checksum = value.parse().unwrap();
+                assert_eq!(parsed.to_bytes(), bytes, "{value}");
+            }
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(matches!(
+            Checksum::parse("md4:00"),
+            Err(ChecksumParseError::UnknownAlgorithm(_))
+        ));
+        assert!(matches!(
+            Checksum::parse("abcd"),
+            Err(ChecksumParseError::MissingAlgorithm(_))
+        ));
+        assert!(matches!(
+            Checksum::parse(&format!("sha256:{}", "e".repeat(52))),
+            Err(ChecksumParseError::InvalidEncoding(_))
+        ));
+        assert!(matches!(
+            Checksum::parse("sha256-AAAA"),
+            Err(ChecksumParseError::InvalidLength {
+                expected: 32,
+                actual: 3,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn nar_of_empty_file() {
+        let dir = TempDir::new();
+        let path = dir.write("empty", "");
+
+        let checksum = path.calculate_nar(HashAlgorithm::Sha256).unwrap();
+        assert_eq!(
+            checksum.to_sri(),
+            "sha256-d6xi4mKdjkX2JFicDIv5niSzpyI0m/Hnm8GGAIU04kY="
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn nar_of_tree() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new();
+        let root = dir.join("tree");
+        dir.write("tree/hello.txt", "hello\n");
+        dir.write("tree/bin/run", "#!/bin/sh\n");
+        std::fs::set_permissions(root.join("bin/run"), std::fs::Permissions::from_mode(0o755))
+            .unwrap();
+        std::os::unix::fs::symlink(Path::new("hello.txt"), root.join("link")).unwrap();
+
+        let checksum = root.calculate_nar(HashAlgorithm::Sha256).unwrap();
+        assert_eq!(
+            checksum.to_sri(),
+            "sha256-/bCK6uy0hORAL+rTb3GN28CUpCawYLe8/GHe98sU6ug="
+        );
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use walkdir::WalkDir;
+
+use super::{relative_path, Checksum, HashAlgorithm, PathChecksum};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ChecksumFileError {
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+
+    #[error("Invalid checksum line {line}: {content}")]
+    InvalidLine { line: usize, content: String },
+}
+
+/// Layout of a checksum file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChecksumFormat {
+    /// `<hex>  <name>`, as written by `sha256sum` and read by `sha256sum -c`
+    #[default]
+    Gnu,
+    /// `SHA256 (<name>) = <hex>`, as written by `sha256sum --tag` and BSD `sha256`
+    Bsd,
+}
+
+/// Entries of a checksum file, in file order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChecksumFile {
+    pub entries: Vec<(String, Checksum)>,
+}
+
+/// Mismatched entry found by [`ChecksumFile::verify`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecksumMismatch {
+    pub name: String,
+    pub expected: Checksum,
+    pub actual: Checksum,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Listed in the checksum file, but not present on disk
+    pub missing: Vec<String>,
+    /// Present on disk, but not listed in the checksum file
+    pub extra: Vec<String>,
+    pub mismatched: Vec<ChecksumMismatch>,
+}
+
+impl VerifyReport {
+    /// Every listed file exists and matches. Extra files are allowed
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.mismatched.is_empty()
+    }
+
+    /// Like [`VerifyReport::is_ok`], but extra files are not allowed either
+    pub fn is_exact(&self) -> bool {
+        self.is_ok() && self.extra.is_empty()
+    }
+}
+
+/// GNU coreutils escape names containing backslashes or newlines and mark the line with `\`
+fn escape_name(name: &str) -> (bool, String) {
+    if name.contains(['\\', '\n', '\r']) {
+        let escaped = name
+            .replace('\\', "\\\\")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r");
+        (true, escaped)
+    } else {
+        (false, name.to_string())
+    }
+}
+
+fn unescape_name(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('\\')) => result.push('\\'),
+            ('\\', Some('n')) => result.push('\n'),
+            ('\\', Some('r')) => result.push('\r'),
+            _ => {
+                result.push(c);
+                continue;
+            }
+        }
+        chars.next();
+    }
+    result
+}
+
+/// Guess the algorithm from names like `SHA512SUMS` or `archive.tar.gz.blake3`
+fn algorithm_from_file_name(path: &Path) -> Option<HashAlgorithm> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    // sha512 and sha256 must be checked before sha1
+    [
+        HashAlgorithm::Sha512,
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Blake3,
+        HashAlgorithm::Md5,
+        HashAlgorithm::Sha1,
+    ]
+    .into_iter()
+    .find(|x| name.contains(x.name()))
+}
+
+/// Hex digest of the right length for the algorithm
+fn is_digest(algorithm: HashAlgorithm, value: &str) -> bool {
+    value.len() == algorithm.output_size() * 2 && value.chars().all(|x| x.is_ascii_hexdigit())
+}
+
+impl ChecksumFile {
+    pub fn new<I>(entries: I) -> Self
+    where
+        I: IntoIterator<Item = (String, Checksum)>,
+    {
+        Self {
+            entries: entries.into_iter().collect(),
+        }
+    }
+
+    /// Parse both GNU and BSD lines. GNU lines do not name the algorithm, so `algorithm` is
+    /// used for them when given, otherwise it is guessed from the digest length. Digests that do
+    /// not match the length of their algorithm are invalid lines
+    pub fn parse(
+        contents: &str,
+        algorithm: Option<HashAlgorithm>,
+    ) -> Result<Self, ChecksumFileError> {
+        let mut entries = Vec::new();
+
+        for (index, line) in contents.lines().enumerate() {
+            let invalid = || ChecksumFileError::InvalidLine {
+                line: index + 1,
+                content: line.to_string(),
+            };
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (escaped, line) = match line.strip_prefix('\\') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+
+            let (name, checksum) = if let Some((tag, rest)) = line
+                .split_once(" (")
+                .filter(|(tag, _)| tag.parse::<HashAlgorithm>().is_ok())
+            {
+                let (name, hex) = rest.rsplit_once(") = ").ok_or_else(invalid)?;
+                let algorithm = tag.parse::<HashAlgorithm>().map_err(|_| invalid())?;
+                if !is_digest(algorithm, hex) {
+                    return Err(invalid());
+                }
+                (name, Checksum::new(algorithm, hex))
+            } else {
+                let (hex, name) = line
+                    .split_once("  ")
+                    .or_else(|| line.split_once(" *"))
+                    .ok_or_else(invalid)?;
+                let algorithm = algorithm
+                    .or_else(|| HashAlgorithm::from_hex_length(hex.len()))
+                    .ok_or_else(invalid)?;
+                if !is_digest(algorithm, hex) {
+                    return Err(invalid());
+                }
+                (name, Checksum::new(algorithm, hex))
+            };
+
+            let name = if escaped {
+                unescape_name(name)
+            } else {
+                name.to_string()
+            };
+            entries.push((name, checksum));
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Read a checksum file. The algorithm of GNU lines is guessed from the file name first
+    pub fn read(path: &Path) -> Result<Self, ChecksumFileError> {
+        Self::parse(
+            &std::fs::read_to_string(path)?,
+            algorithm_from_file_name(path),
+        )
+    }
+
+    pub fn format(&self, format: ChecksumFormat) -> String {
+        self.entries
+            .iter()
+            .map(|(name, checksum)| {
+                let (escaped, name) = escape_name(name);
+                let marker = if escaped { "\\" } else { "" };
+                match format {
+                    ChecksumFormat::Gnu => format!("{marker}{}  {name}\n", checksum.get()),
+                    ChecksumFormat::Bsd => format!(
+                        "{marker}{} ({name}) = {}\n",
+                        checksum.algorithm().bsd_name(),
+                        checksum.get()
+                    ),
+                }
+            })
+            .collect()
+    }
+
+    pub fn write(&self, path: &Path, format: ChecksumFormat) -> Result<(), std::io::Error> {
+        use std::io::Write;
+
+        let mut file = std::fs::File::create(path)?;
+        file.write_all(self.format(format).as_bytes())
+    }
+
+    /// Check the files in `dir` against the entries. Names are paths relative to `dir`
+    pub fn verify(&self, dir: &Path) -> Result<VerifyReport, std::io::Error> {
+        let mut report = VerifyReport::default();
+
+        for (name, expected) in &self.entries {
+            let path = dir.join(name);
+            if !path.is_file() {
+                report.missing.push(name.clone());
+                continue;
+            }
+
+            let actual = path.calculate(expected.algorithm())?;
+            if &actual != expected {
+                report.mismatched.push(ChecksumMismatch {
+                    name: name.clone(),
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+
+        let listed = self
+            .entries
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<BTreeSet<_>>();
+        for entry in WalkDir::new(dir).sort_by_file_name() {
+            let entry = entry?;
+            if entry.file_type().is_dir() {
+                continue;
+            }
+            let name = relative_path(dir, entry.path());
+            if !listed.contains(name.as_str()) {
+                report.extra.push(name);
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Read the checksum file and verify the directory containing it. The checksum file itself
+    /// is not reported as extra
+    pub fn verify_file(path: &Path) -> Result<VerifyReport, ChecksumFileError> {
+        let dir = path
+            .parent()
+            .filter(|x| !x.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let mut report = Self::read(path)?.verify(dir)?;
+
+        let own_name = relative_path(dir, path);
+        report.extra.retain(|x| *x != own_name);
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    fn invalid_line(result: Result<ChecksumFile, ChecksumFileError>) -> Option<usize> {
+        match result {
+            Err(ChecksumFileError::InvalidLine { line, .. }) => Some(line),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn parses_gnu_lines() {
+        let (sha256, md5) = ("ab".repeat(32), "CD".repeat(16));
+        let contents = format!("# comment\n\n{sha256}  a.txt\n{md5} *dir/b.bin\n");
+
+        let file = ChecksumFile::parse(&contents, None).unwrap();
+        assert_eq!(
+            file.entries,
+            [
+                (
+                    "a.txt".to_string(),
+                    Checksum::new(HashAlgorithm::Sha256, &sha256)
+                ),
+                (
+                    "dir/b.bin".to_string(),
+                    Checksum::new(HashAlgorithm::Md5, &md5)
+                ),
+            ]
+        );
+        assert_eq!(file.entries[1].1.get(), "cd".repeat(16));
+
+        let file = ChecksumFile::parse(&format!("{sha256}  a.txt\n"), Some(HashAlgorithm::Blake3));
+        assert_eq!(
+            file.unwrap().entries[0].1.algorithm(),
+            HashAlgorithm::Blake3
+        );
+    }
+
+    #[test]
+    fn parses_bsd_lines() {
+        let (sha512, sha1) = ("0f".repeat(64), "1e".repeat(20));
+        let contents = format!("SHA512 (a (1).txt) = {sha512}\nSHA1 (b) = {sha1}\n");
+
+        let file = ChecksumFile::parse(&contents, Some(HashAlgorithm::Sha256)).unwrap();
+        assert_eq!(
+            file.entries,
+            [
+                (
+                    "a (1).txt".to_string(),
+                    Checksum::new(HashAlgorithm::Sha512, sha512)
+                ),
+                ("b".to_string(), Checksum::new(HashAlgorithm::Sha1, sha1)),
+            ]
+        );
+        assert_eq!(
+            ChecksumFile::parse(&file.format(ChecksumFormat::Bsd), None).unwrap(),
+            file
+        );
+    }
+
+    #[test]
+    fn parses_escaped_names() {
+        let sha256 = "ab".repeat(32);
+        let name = "dir\\name\nwith newline";
+
+        let file = ChecksumFile::parse(&format!("\\{sha256}  dir\\\\name\\nwith newline\n"), None);
+        assert_eq!(file.unwrap().entries[0].0, name);
+
+        let file = ChecksumFile::new([(
+            name.to_string(),
+            Checksum::new(HashAlgorithm::Sha256, &sha256),
+        )]);
+        for format in [ChecksumFormat::Gnu, ChecksumFormat::Bsd] {
+            let contents = file.format(format);
+            assert!(contents.starts_with('\\'));
+            assert_eq!(contents.lines().count(), 1);
+            assert_eq!(ChecksumFile::parse(&contents, None).unwrap(), file);
+        }
+    }
+
+    #[test]
+    fn rejects_digests_of_wrong_length() {
+        let sha256 = "ab".repeat(32);
+
+        let contents = format!("{sha256}  a.txt\n{}  b.txt\n", "ab".repeat(20));
+        assert_eq!(
+            invalid_line(ChecksumFile::parse(&contents, Some(HashAlgorithm::Sha256))),
+            Some(2)
+        );
+        let contents = format!("{sha256}  a.txt\n");
+        assert_eq!(
+            invalid_line(ChecksumFile::parse(&contents, Some(HashAlgorithm::Sha512))),
+            Some(1)
+        );
+        assert_eq!(
+            invalid_line(ChecksumFile::parse("abc  a.txt\n", None)),
+            Some(1)
+        );
+        let contents = format!("{}  a.txt\n", "xy".repeat(32));
+        assert_eq!(invalid_line(ChecksumFile::parse(&contents, None)), Some(1));
+
+        let contents = format!("SHA512 (a.txt) = {sha256}\n");
+        assert_eq!(invalid_line(ChecksumFile::parse(&contents, None)), Some(1));
+        let contents = format!("MD5 (a.txt) = {sha256}\n");
+        assert_eq!(invalid_line(ChecksumFile::parse(&contents, None)), Some(1));
+
+        let dir = TempDir::new();
+        let path = dir.write("SHA256SUMS", format!("{}  a.txt\n", "ab".repeat(20)));
+        assert_eq!(invalid_line(ChecksumFile::read(&path)), Some(1));
+        let path = dir.write("SHA1SUMS", format!("{}  a.txt\n", "ab".repeat(20)));
+        assert_eq!(
+            ChecksumFile::read(&path).unwrap().entries[0].1.algorithm(),
+            HashAlgorithm::Sha1
+        );
+    }
+
+    #[test]
+    fn reports_missing_extra_and_mismatched() {
+        let dir = TempDir::new();
+        let a = dir.write("a.txt", "a");
+        dir.write("b.txt", "b");
+        dir.write("sub/c.txt", "c");
+        let expected_b = a.calculate(HashAlgorithm::Sha256).unwrap();
+
+        let file = ChecksumFile::new([
+            (
+                "a.txt".to_string(),
+                a.calculate(HashAlgorithm::Sha256).unwrap(),
+            ),
+            ("b.txt".to_string(), expected_b.clone()),
+            ("gone.txt".to_string(), expected_b.clone()),
+        ]);
+        let report = file.verify(dir.path()).unwrap();
+        assert_eq!(report.missing, ["gone.txt"]);
+        assert_eq!(report.extra, ["sub/c.txt"]);
+        assert_eq!(
+            report.mismatched,
+            [ChecksumMismatch {
+                name: "b.txt".to_string(),
+                expected: expected_b,
+                actual: dir.join("b.txt").calculate(HashAlgorithm::Sha256).unwrap(),
+            }]
+        );
+        assert!(!report.is_ok());
+
+        let file = ChecksumFile::new(file.entries.into_iter().take(1));
+        let report = file.verify(dir.path()).unwrap();
+        assert!(report.is_ok());
+        assert!(!report.is_exact());
+        assert_eq!(report.extra, ["b.txt", "sub/c.txt"]);
+    }
+
+    #[test]
+    fn verify_file_skips_itself() {
+        let dir = TempDir::new();
+        let a = dir.write("a.txt", "a");
+        let file = ChecksumFile::new([(
+            "a.txt".to_string(),
+            a.calculate(HashAlgorithm::Md5).unwrap(),
+        )]);
+        let path = dir.join("MD5SUMS");
+        file.write(&path, ChecksumFormat::Gnu).unwrap();
+
+        let report = ChecksumFile::verify_file(&path).unwrap();
+        assert!(report.is_exact());
+    }
+}
@@ -0,0 +1,342 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+mod algorithm;
+mod encoding;
+mod file;
+mod nar;
+mod read;
+#[cfg(feature = "checksums-sign")]
+mod sign;
+mod tree;
+
+pub use crate::filter::Filter;
+pub use algorithm::{HashAlgorithm, UnknownAlgorithm};
+pub use encoding::ChecksumParseError;
+pub use file::{ChecksumFile, ChecksumFileError, ChecksumFormat, ChecksumMismatch, VerifyReport};
+#[cfg(feature = "checksums-mmap")]
+pub use read::MMAP_THRESHOLD;
+pub use sha2;
+#[cfg(feature = "checksums-sign")]
+pub use sign::{
+    Gpg, MinisignPublicKey, MinisignSecretKey, MinisignSigner, SignError, SignatureVerifier, Signer,
+};
+
+/// Hex encoded digest together with the algorithm that produced it
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct Checksum {
+    algorithm: HashAlgorithm,
+    value: String,
+}
+
+pub const UNKNOWN_FILENAME: &str = "unknown";
+
+fn strfilename(path: &std::path::Path) -> String {
+    path.file_name()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or(UNKNOWN_FILENAME.to_string())
+}
+
+/// Relative path with `/` separators, independent of the platform
+pub(crate) fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|x| x.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Hash the file with all the algorithms in a single pass
+fn hash_file(path: &Path, algorithms: &[HashAlgorithm]) -> Result<Vec<Checksum>, std::io::Error> {
+    let mut hashers = algorithms.iter().map(|x| x.hasher()).collect::<Vec<_>>();
+    read::read_chunks(path, |chunk| {
+        hashers.iter_mut().for_each(|hasher| hasher.update(chunk))
+    })?;
+
+    Ok(algorithms
+        .iter()
+        .zip(hashers)
+        .map(|(algorithm, hasher)| Checksum::from_bytes(*algorithm, &hasher.finalize()))
+        .collect())
+}
+
+pub trait PathChecksum {
+    fn calculate_sha256(&self) -> Result<Checksum, std::io::Error>;
+    /// Only the files the filter accepts are hashed. Every directory is descended into
+    fn calculate_sha256_filtered(
+        &self,
+        filter: fn(&std::path::Path) -> bool,
+    ) -> Result<Checksum, std::io::Error>;
+    fn calculate_entries_sha256(&self) -> Result<HashMap<String, Checksum>, std::io::Error>;
+
+    fn calculate(&self, algorithm: HashAlgorithm) -> Result<Checksum, std::io::Error>;
+    fn calculate_filtered(
+        &self,
+        algorithm: HashAlgorithm,
+        filter: &Filter,
+    ) -> Result<Checksum, std::io::Error>;
+
+    /// One checksum per algorithm, in the same order, reading every file only once
+    fn calculate_many(&self, algorithms: &[HashAlgorithm])
+        -> Result<Vec<Checksum>, std::io::Error>;
+    fn calculate_many_filtered(
+        &self,
+        algorithms: &[HashAlgorithm],
+        filter: &Filter,
+    ) -> Result<Vec<Checksum>, std::io::Error>;
+
+    fn calculate_entries(
+        &self,
+        algorithm: HashAlgorithm,
+    ) -> Result<HashMap<String, Checksum>, std::io::Error>;
+
+    /// Checksums of all the files below the directory, keyed by `/` separated paths relative to
+    /// it. A single file is keyed by its file name
+    fn calculate_entries_recursive(
+        &self,
+        algorithm: HashAlgorithm,
+    ) -> Result<BTreeMap<String, Checksum>, std::io::Error>;
+    fn calculate_entries_recursive_filtered(
+        &self,
+        algorithm: HashAlgorithm,
+        filter: &Filter,
+    ) -> Result<BTreeMap<String, Checksum>, std::io::Error>;
+    fn calculate_entries_sha256_recursive(
+        &self,
+    ) -> Result<BTreeMap<String, Checksum>, std::io::Error>;
+
+    /// Digest covering relative paths, file types, executable bits and symlink targets, unlike
+    /// [`PathChecksum::calculate`] which only combines the file contents
+    fn calculate_tree(&self, algorithm: HashAlgorithm) -> Result<Checksum, std::io::Error>;
+
+    fn calculate_tree_filtered(
+        &self,
+        algorithm: HashAlgorithm,
+        filter: &Filter,
+    ) -> Result<Checksum, std::io::Error>;
+
+    /// Digest of the Nix archive (NAR) serialization. With SHA-256, [`Checksum::to_sri`]
+    /// gives the Nix `narHash`
+    fn calculate_nar(&self, algorithm: HashAlgorithm) -> Result<Checksum, std::io::Error>;
+}
+
+impl PathChecksum for Path {
+    fn calculate_many_filtered(
+        &self,
+        algorithms: &[HashAlgorithm],
+        filter: &Filter,
+    ) -> Result<Vec<Checksum>, std::io::Error> {
+        if self.is_file() {
+            hash_file(self, algorithms)
+        } else {
+            let files = filter.walk_files(self)?;
+
+            // digest of the concatenated hex digests of all the files
+            let mut hashers = algorithms.iter().map(|x| x.hasher()).collect::<Vec<_>>();
+            for checksums in read::map_ordered(&files, |file| hash_file(file, algorithms)) {
+                for (hasher, checksum) in hashers.iter_mut().zip(checksums?) {
+                    hasher.update(checksum.get().as_bytes());
+                }
+            }
+
+            Ok(algorithms
+                .iter()
+                .zip(hashers)
+                .map(|(algorithm, hasher)| Checksum::from_bytes(*algorithm, &hasher.finalize()))
+                .collect())
+        }
+    }
+
+    fn calculate_many(
+        &self,
+        algorithms: &[HashAlgorithm],
+    ) -> Result<Vec<Checksum>, std::io::Error> {
+        self.calculate_many_filtered(algorithms, &Filter::new())
+    }
+
+    fn calculate_filtered(
+        &self,
+        algorithm: HashAlgorithm,
+        filter: &Filter,
+    ) -> Result<Checksum, std::io::Error> {
+        let mut checksums = self.calculate_many_filtered(&[algorithm], filter)?;
+        Ok(checksums.remove(0))
+    }
+
+    fn calculate(&self, algorithm: HashAlgorithm) -> Result<Checksum, std::io::Error> {
+        self.calculate_filtered(algorithm, &Filter::new())
+    }
+
+    fn calculate_entries(
+        &self,
+        algorithm: HashAlgorithm,
+    ) -> Result<HashMap<String, Checksum>, std::io::Error> {
+        if !self.is_dir() {
+            let checksum = self.calculate(algorithm)?;
+            return Ok(HashMap::from([(strfilename(self), checksum)]));
+        }
+
+        let entries = self
+            .read_dir()?
+            .map(|entry| entry.map(|x| x.path()))
+            .collect::<Result<Vec<PathBuf>, _>>()?;
+
+        read::map_ordered(&entries, |entry_path| {
+            Ok((strfilename(entry_path), entry_path.calculate(algorithm)?))
+        })
+        .into_iter()
+        .collect()
+    }
+
+    fn calculate_entries_recursive_filtered(
+        &self,
+        algorithm: HashAlgorithm,
+        filter: &Filter,
+    ) -> Result<BTreeMap<String, Checksum>, std::io::Error> {
+        if !self.is_dir() {
+            let checksum = self.calculate(algorithm)?;
+            return Ok(BTreeMap::from([(strfilename(self), checksum)]));
+        }
+
+        let files = filter.walk_files(self)?;
+
+        read::map_ordered(&files, |file| {
+            Ok((relative_path(self, file), file.calculate(algorithm)?))
+        })
+        .into_iter()
+        .collect()
+    }
+
+    fn calculate_entries_recursive(
+        &self,
+        algorithm: HashAlgorithm,
+    ) -> Result<BTreeMap<String, Checksum>, std::io::Error> {
+        self.calculate_entries_recursive_filtered(algorithm, &Filter::new())
+    }
+
+    fn calculate_entries_sha256_recursive(
+        &self,
+    ) -> Result<BTreeMap<String, Checksum>, std::io::Error> {
+        self.calculate_entries_recursive(HashAlgorithm::Sha256)
+    }
+
+    fn calculate_tree(&self, algorithm: HashAlgorithm) -> Result<Checksum, std::io::Error> {
+        tree::tree_checksum(self, algorithm, &Filter::new())
+    }
+
+    fn calculate_tree_filtered(
+        &self,
+        algorithm: HashAlgorithm,
+        filter: &Filter,
+    ) -> Result<Checksum, std::io::Error> {
+        tree::tree_checksum(self, algorithm, filter)
+    }
+
+    fn calculate_nar(&self, algorithm: HashAlgorithm) -> Result<Checksum, std::io::Error> {
+        let digest = nar::nar_digest(self, algorithm.hasher())?;
+        Ok(Checksum::from_bytes(algorithm, &digest))
+    }
+
+    fn calculate_sha256_filtered(
+        &self,
+        filter: fn(&std::path::Path) -> bool,
+    ) -> Result<Checksum, std::io::Error> {
+        self.calculate_filtered(HashAlgorithm::Sha256, &Filter::new().include_if(filter))
+    }
+
+    fn calculate_entries_sha256(&self) -> Result<HashMap<String, Checksum>, std::io::Error> {
+        self.calculate_entries(HashAlgorithm::Sha256)
+    }
+
+    fn calculate_sha256(&self) -> Result<Checksum, std::io::Error> {
+        self.calculate(HashAlgorithm::Sha256)
+    }
+}
+
+impl Checksum {
+    /// Hex encoded digest. Not validated against the algorithm
+    pub fn new<S>(algorithm: HashAlgorithm, value: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            algorithm,
+            value: value.into().to_lowercase(),
+        }
+    }
+
+    pub fn from_bytes(algorithm: HashAlgorithm, digest: &[u8]) -> Self {
+        Self {
+            algorithm,
+            value: algorithm::hex(digest),
+        }
+    }
+
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    pub fn get(&self) -> &str {
+        self.value.as_str()
+    }
+
+    pub fn string(&self) -> String {
+        self.value.clone()
+    }
+}
+
+pub trait ChecksumsToFile {
+    /// Write in the GNU coreutils format, readable by `sha256sum -c`
+    fn save_checksum(&self, path: &std::path::Path) -> Result<(), std::io::Error>;
+    fn save_checksum_with_format(
+        &self,
+        path: &std::path::Path,
+        format: ChecksumFormat,
+    ) -> Result<(), std::io::Error>;
+}
+
+impl<T> ChecksumsToFile for T
+where
+    T: Iterator<Item = (String, Checksum)> + Clone
+{
+    fn save_checksum(&self, path: &std::path::Path) -> Result<(), std::io::Error> {
+        self.save_checksum_with_format(path, ChecksumFormat::Gnu)
+    }
+
+    fn save_checksum_with_format(
+        &self,
+        path: &std::path::Path,
+        format: ChecksumFormat,
+    ) -> Result<(), std::io::Error> {
+        ChecksumFile::new(self.clone()).write(path, format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    fn tree(files: &[&str]) -> TempDir {
+        let dir = TempDir::new();
+        for file in files {
+            dir.write(file, file);
+        }
+        dir
+    }
+
+    #[test]
+    fn sha256_filter_applies_to_nested_files() {
+        let all = tree(&["a.txt", "c.rs", "nested/b.txt", "nested/d.rs"]);
+        let txt = tree(&["a.txt", "nested/b.txt"]);
+        let only_a = tree(&["a.txt"]);
+
+        let filtered = all
+            .path()
+            .calculate_sha256_filtered(|path| path.extension().is_some_and(|x| x == "txt"))
+            .unwrap();
+        assert_eq!(filtered, txt.path().calculate_sha256().unwrap());
+        assert_ne!(filtered, only_a.path().calculate_sha256().unwrap());
+    }
+}
@@ -0,0 +1,110 @@
+use std::path::Path;
+
+use super::algorithm::Hasher;
+use super::read;
+
+/// Writes the Nix archive serialization of a path into a hasher, without buffering file contents
+struct NarWriter {
+    hasher: Hasher,
+}
+
+impl NarWriter {
+    fn padding(&mut self, length: u64) {
+        let padding = (8 - length % 8) % 8;
+        self.hasher.update(&[0; 8][..padding as usize]);
+    }
+
+    fn bytes(&mut self, value: &[u8]) {
+        self.hasher.update(&(value.len() as u64).to_le_bytes());
+        self.hasher.update(value);
+        self.padding(value.len() as u64);
+    }
+
+    fn str(&mut self, value: &str) {
+        self.bytes(value.as_bytes());
+    }
+
+    fn node(&mut self, path: &Path) -> Result<(), std::io::Error> {
+        let metadata = std::fs::symlink_metadata(path)?;
+        self.str("(");
+        self.str("type");
+
+        if metadata.file_type().is_symlink() {
+            self.str("symlink");
+            self.str("target");
+            self.bytes(&os_bytes(std::fs::read_link(path)?.as_os_str()));
+        } else if metadata.is_dir() {
+            self.str("directory");
+
+            let mut entries = std::fs::read_dir(path)?
+                .map(|entry| entry.map(|x| x.file_name()))
+                .collect::<Result<Vec<_>, _>>()?;
+            entries.sort_by_key(|x| os_bytes(x));
+            for name in entries {
+                self.str("entry");
+                self.str("(");
+                self.str("name");
+                self.bytes(&os_bytes(&name));
+                self.str("node");
+                self.node(&path.join(name))?;
+                self.str(")");
+            }
+        } else {
+            self.str("regular");
+            if is_executable(&metadata) {
+                self.str("executable");
+                self.str("");
+            }
+            self.str("contents");
+
+            // same as `bytes`, but streamed. The length is taken from the data actually read
+            let length = metadata.len();
+            self.hasher.update(&length.to_le_bytes());
+            let mut read_length = 0;
+            read::read_chunks(path, |chunk| {
+                read_length += chunk.len() as u64;
+                self.hasher.update(chunk);
+            })?;
+            if read_length != length {
+                return Err(std::io::Error::other(format!(
+                    "{} changed while hashing",
+                    path.display()
+                )));
+            }
+            self.padding(length);
+        }
+
+        self.str(")");
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn os_bytes(value: &std::ffi::OsStr) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    value.as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+fn os_bytes(value: &std::ffi::OsStr) -> Vec<u8> {
+    value.to_string_lossy().as_bytes().to_vec()
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o100 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    false
+}
+
+/// Digest of the NAR serialization of `path`, as used by Nix for `narHash`
+pub(crate) fn nar_digest(path: &Path, hasher: Hasher) -> Result<Vec<u8>, std::io::Error> {
+    let mut writer = NarWriter { hasher };
+    writer.str("nix-archive-1");
+    writer.node(path)?;
+    Ok(writer.hasher.finalize())
+}
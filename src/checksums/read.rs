@@ -0,0 +1,56 @@
+use std::io::Read;
+use std::path::Path;
+
+/// Size of the buffer used to stream files into the hasher
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Files at least this large are memory-mapped instead of streamed
+#[cfg(feature = "checksums-mmap")]
+pub const MMAP_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// Feed the file contents to `update` in chunks, using constant memory
+pub(crate) fn read_chunks<F>(path: &Path, mut update: F) -> Result<(), std::io::Error>
+where
+    F: FnMut(&[u8]),
+{
+    let mut file = std::fs::File::open(path)?;
+
+    #[cfg(feature = "checksums-mmap")]
+    if file.metadata()?.len() >= MMAP_THRESHOLD {
+        // SAFETY: the map is read-only and dropped before returning. A file truncated
+        // concurrently by another process is undefined behaviour, same as with any mmap user
+        if let Ok(map) = unsafe { memmap2::Mmap::map(&file) } {
+            update(&map);
+            return Ok(());
+        }
+    }
+
+    let mut buffer = vec![0; BUFFER_SIZE];
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(read) => update(&buffer[..read]),
+            Err(error) if error.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// Apply `f` to every item, in parallel with `checksums-parallel`. Results keep the input order
+pub(crate) fn map_ordered<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync + Send,
+{
+    #[cfg(feature = "checksums-parallel")]
+    {
+        use rayon::prelude::*;
+        items.par_iter().map(f).collect()
+    }
+
+    #[cfg(not(feature = "checksums-parallel"))]
+    {
+        items.iter().map(f).collect()
+    }
+}
@@ -0,0 +1,672 @@
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Blake2b512, Digest};
+use xshell::{cmd, Shell};
+
+use super::read;
+
+/// Signature algorithm of minisign keys and legacy (not prehashed) signatures
+const ED25519: [u8; 2] = *b"Ed";
+/// Signature algorithm of signatures over the BLAKE2b-512 digest of the file
+const ED25519_HASHED: [u8; 2] = *b"ED";
+const KDF_SCRYPT: [u8; 2] = *b"Sc";
+const KDF_NONE: [u8; 2] = [0, 0];
+const CHECKSUM_BLAKE2: [u8; 2] = *b"B2";
+
+const KEY_ID_BYTES: usize = 8;
+/// Key id, secret key and its checksum. The part of the secret key file encrypted with scrypt
+const KEYNUM_SK_BYTES: usize = KEY_ID_BYTES + 64 + 32;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SignError {
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+
+    #[error(transparent)]
+    XShellError(#[from] xshell::Error),
+
+    #[error("Invalid key: {0}")]
+    InvalidKey(&'static str),
+
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(&'static str),
+
+    #[error("Secret key is encrypted, password required")]
+    PasswordRequired,
+
+    #[error("Could not decrypt the secret key, wrong password?")]
+    WrongPassword,
+
+    #[error("Signature was created with key {signature_key} instead of {key}")]
+    KeyMismatch { key: String, signature_key: String },
+
+    #[error("Signature verification failed: {0}")]
+    VerificationFailed(String),
+}
+
+/// Creates detached signatures of files
+pub trait Signer {
+    /// Extension appended to the file name by [`Signer::sign_file`], without the dot
+    fn extension(&self) -> &'static str;
+
+    fn sign_file_to(&self, file: &Path, signature: &Path) -> Result<(), SignError>;
+
+    /// Write the signature next to the file, e.g. `SHA256SUMS.minisig`
+    fn sign_file(&self, file: &Path) -> Result<PathBuf, SignError> {
+        let mut signature = file.as_os_str().to_owned();
+        signature.push(".");
+        signature.push(self.extension());
+        let signature = PathBuf::from(signature);
+
+        self.sign_file_to(file, &signature)?;
+        Ok(signature)
+    }
+}
+
+/// Checks detached signatures of files
+pub trait SignatureVerifier {
+    fn verify_file(&self, file: &Path, signature: &Path) -> Result<(), SignError>;
+}
+
+fn key_id_hex(key_id: &[u8; KEY_ID_BYTES]) -> String {
+    format!("{:016X}", u64::from_le_bytes(*key_id))
+}
+
+/// Decode the base64 line following the optional `untrusted comment:` line. Returns the
+/// remaining lines too
+fn decode_lines(contents: &str) -> Option<(Vec<u8>, Vec<&str>)> {
+    let mut lines = contents.lines().map(str::trim_end);
+    let encoded = match lines.next()? {
+        line if line.starts_with("untrusted comment:") => lines.next()?,
+        line => line,
+    };
+
+    let decoded = BASE64.decode(encoded.trim()).ok()?;
+    Some((decoded, lines.collect()))
+}
+
+/// scrypt parameters derived from the libsodium limits stored in the key, same as minisign
+fn scrypt_params(opslimit: u64, memlimit: u64) -> Result<scrypt::Params, SignError> {
+    let opslimit = opslimit.max(32768);
+    let r = 8u64;
+    let log_n_for = |max_n: u64| (1..63).find(|x| (1u64 << x) > max_n / 2).unwrap_or(63);
+
+    let (log_n, p) = if opslimit < memlimit / 32 {
+        (log_n_for(opslimit / (r * 4)), 1)
+    } else {
+        let log_n = log_n_for(memlimit / (r * 128));
+        let max_rp = ((opslimit / 4) / (1u64 << log_n)).min(0x3fff_ffff);
+        (log_n, max_rp / r)
+    };
+
+    // The length is only used for PHC strings, `scrypt()` fills the whole output buffer
+    scrypt::Params::new(
+        log_n as u8,
+        r as u32,
+        p as u32,
+        scrypt::Params::RECOMMENDED_LEN,
+    )
+    .map_err(|_| SignError::InvalidKey("unsupported scrypt parameters"))
+}
+
+/// minisign secret key, as created by `minisign -G`
+pub struct MinisignSecretKey {
+    key_id: [u8; KEY_ID_BYTES],
+    keypair: ed25519_compact::KeyPair,
+}
+
+impl MinisignSecretKey {
+    /// Parse the contents of a secret key file. Keys created with `-W` have no password
+    pub fn decode(contents: &str, password: Option<&str>) -> Result<Self, SignError> {
+        let (decoded, _) =
+            decode_lines(contents).ok_or(SignError::InvalidKey("not a minisign key"))?;
+        if decoded.len() != 6 + 32 + 16 + KEYNUM_SK_BYTES {
+            return Err(SignError::InvalidKey("unexpected secret key length"));
+        }
+        let (header, rest) = decoded.split_at(6);
+        let (salt, rest) = rest.split_at(32);
+        let (limits, keynum_sk) = rest.split_at(16);
+        if header[0..2] != ED25519 || header[4..6] != CHECKSUM_BLAKE2 {
+            return Err(SignError::InvalidKey("unsupported algorithm"));
+        }
+
+        let mut keynum_sk = keynum_sk.to_vec();
+        match [header[2], header[3]] {
+            KDF_NONE => (),
+            KDF_SCRYPT => {
+                let password = password.ok_or(SignError::PasswordRequired)?;
+                let opslimit = u64::from_le_bytes(limits[0..8].try_into().unwrap_or_default());
+                let memlimit = u64::from_le_bytes(limits[8..16].try_into().unwrap_or_default());
+
+                let mut stream = [0u8; KEYNUM_SK_BYTES];
+                scrypt::scrypt(
+                    password.as_bytes(),
+                    salt,
+                    &scrypt_params(opslimit, memlimit)?,
+                    &mut stream,
+                )
+                .map_err(|_| SignError::InvalidKey("scrypt failed"))?;
+                keynum_sk
+                    .iter_mut()
+                    .zip(stream)
+                    .for_each(|(byte, key)| *byte ^= key);
+            }
+            _ => return Err(SignError::InvalidKey("unsupported key derivation")),
+        }
+
+        let (key_id, rest) = keynum_sk.split_at(KEY_ID_BYTES);
+        let (secret, checksum) = rest.split_at(64);
+        let expected = Blake2b::<U32>::new()
+            .chain_update(ED25519)
+            .chain_update(key_id)
+            .chain_update(secret)
+            .finalize();
+        if expected.as_slice() != checksum {
+            return Err(match password {
+                Some(_) => SignError::WrongPassword,
+                None => SignError::InvalidKey("checksum mismatch"),
+            });
+        }
+
+        Ok(Self {
+            key_id: key_id.try_into().unwrap_or_default(),
+            keypair: ed25519_compact::KeyPair::from_slice(secret)
+                .map_err(|_| SignError::InvalidKey("invalid ed25519 key"))?,
+        })
+    }
+
+    pub fn from_file(path: &Path, password: Option<&str>) -> Result<Self, SignError> {
+        Self::decode(&std::fs::read_to_string(path)?, password)
+    }
+
+    /// Create a key from a 32 byte ed25519 seed
+    pub fn from_seed(key_id: [u8; KEY_ID_BYTES], seed: [u8; 32]) -> Result<Self, SignError> {
+        if seed.iter().all(|x| *x == 0) {
+            return Err(SignError::InvalidKey("all-zero seed"));
+        }
+        Ok(Self {
+            key_id,
+            keypair: ed25519_compact::KeyPair::from_seed(ed25519_compact::Seed::new(seed)),
+        })
+    }
+
+    pub fn key_id(&self) -> String {
+        key_id_hex(&self.key_id)
+    }
+
+    /// Contents of the matching public key file (`minisign.pub`)
+    pub fn public_key(&self) -> String {
+        let mut encoded = ED25519.to_vec();
+        encoded.extend(self.key_id);
+        encoded.extend(*self.keypair.pk);
+        format!(
+            "untrusted comment: minisign public key {}\n{}\n",
+            self.key_id(),
+            BASE64.encode(encoded)
+        )
+    }
+
+    /// Unencrypted secret key file contents, readable by `minisign` and [`Self::decode`]
+    pub fn encode_unencrypted(&self) -> String {
+        let checksum = Blake2b::<U32>::new()
+            .chain_update(ED25519)
+            .chain_update(self.key_id)
+            .chain_update(*self.keypair.sk)
+            .finalize();
+
+        let mut encoded = ED25519.to_vec();
+        encoded.extend(KDF_NONE);
+        encoded.extend(CHECKSUM_BLAKE2);
+        encoded.extend([0; 32 + 16]);
+        encoded.extend(self.key_id);
+        encoded.extend(*self.keypair.sk);
+        encoded.extend(checksum);
+        format!(
+            "untrusted comment: minisign encrypted secret key\n{}\n",
+            BASE64.encode(encoded)
+        )
+    }
+}
+
+/// Signs files with a minisign key. Signatures are always prehashed, as in minisign 0.11
+pub struct MinisignSigner {
+    key: MinisignSecretKey,
+    trusted_comment: Option<String>,
+}
+
+impl MinisignSigner {
+    pub fn new(key: MinisignSecretKey) -> Self {
+        Self {
+            key,
+            trusted_comment: None,
+        }
+    }
+
+    /// Signed comment. Defaults to `timestamp:<unix time>\tfile:<name>\thashed`
+    pub fn with_trusted_comment<S>(&mut self, comment: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.trusted_comment = Some(comment.into());
+        self
+    }
+
+    /// Contents of the signature file for `file`
+    pub fn sign(&self, file: &Path) -> Result<String, SignError> {
+        let mut hasher = Blake2b512::new();
+        read::read_chunks(file, |chunk| hasher.update(chunk))?;
+        let signature = self.key.keypair.sk.sign(hasher.finalize(), None);
+
+        let trusted_comment = match &self.trusted_comment {
+            Some(comment) => comment.clone(),
+            None => format!(
+                "timestamp:{}\tfile:{}\thashed",
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |x| x.as_secs()),
+                super::strfilename(file)
+            ),
+        };
+        let mut global = signature.to_vec();
+        global.extend(trusted_comment.as_bytes());
+        let global_signature = self.key.keypair.sk.sign(global, None);
+
+        let mut encoded = ED25519_HASHED.to_vec();
+        encoded.extend(self.key.key_id);
+        encoded.extend(*signature);
+        Ok(format!(
+            "untrusted comment: signature from minisign secret key\n{}\ntrusted comment: {trusted_comment}\n{}\n",
+            BASE64.encode(encoded),
+            BASE64.encode(*global_signature)
+        ))
+    }
+}
+
+impl Signer for MinisignSigner {
+    fn extension(&self) -> &'static str {
+        "minisig"
+    }
+
+    fn sign_file_to(&self, file: &Path, signature: &Path) -> Result<(), SignError> {
+        std::fs::write(signature, self.sign(file)?)?;
+        Ok(())
+    }
+}
+
+/// minisign public key, verifying both prehashed and legacy signatures
+pub struct MinisignPublicKey {
+    key_id: [u8; KEY_ID_BYTES],
+    key: ed25519_compact::PublicKey,
+}
+
+impl MinisignPublicKey {
+    /// Parse the contents of `minisign.pub` or the bare base64 key (as passed to `minisign -P`)
+    pub fn decode(contents: &str) -> Result<Self, SignError> {
+        let (decoded, _) =
+            decode_lines(contents).ok_or(SignError::InvalidKey("not a minisign key"))?;
+        if decoded.len() != 2 + KEY_ID_BYTES + 32 || decoded[0..2] != ED25519 {
+            return Err(SignError::InvalidKey("not an ed25519 minisign public key"));
+        }
+
+        Ok(Self {
+            key_id: decoded[2..10].try_into().unwrap_or_default(),
+            key: ed25519_compact::PublicKey::from_slice(&decoded[10..])
+                .map_err(|_| SignError::InvalidKey("invalid ed25519 key"))?,
+        })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, SignError> {
+        Self::decode(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn key_id(&self) -> String {
+        key_id_hex(&self.key_id)
+    }
+
+    /// Check the signature file contents. Returns the trusted comment
+    pub fn verify(&self, file: &Path, signature: &str) -> Result<String, SignError> {
+        let (decoded, rest) = decode_lines(signature)
+            .ok_or(SignError::InvalidSignature("not a minisign signature"))?;
+        if decoded.len() != 2 + KEY_ID_BYTES + 64 {
+            return Err(SignError::InvalidSignature("unexpected length"));
+        }
+        let (algorithm, rest_decoded) = decoded.split_at(2);
+        let (key_id, signature_bytes) = rest_decoded.split_at(KEY_ID_BYTES);
+        if key_id != self.key_id {
+            return Err(SignError::KeyMismatch {
+                key: self.key_id(),
+                signature_key: key_id_hex(&key_id.try_into().unwrap_or_default()),
+            });
+        }
+        let signature = ed25519_compact::Signature::from_slice(signature_bytes)
+            .map_err(|_| SignError::InvalidSignature("invalid ed25519 signature"))?;
+
+        let failed = |what: &str| SignError::VerificationFailed(what.to_string());
+        if algorithm == ED25519_HASHED {
+            let mut hasher = Blake2b512::new();
+            read::read_chunks(file, |chunk| hasher.update(chunk))?;
+            self.key
+                .verify(hasher.finalize(), &signature)
+                .map_err(|_| failed("file signature does not match"))?;
+        } else if algorithm == ED25519 {
+            self.key
+                .verify(std::fs::read(file)?, &signature)
+                .map_err(|_| failed("file signature does not match"))?;
+        } else {
+            return Err(SignError::InvalidSignature("unsupported algorithm"));
+        }
+
+        let trusted_comment = rest
+            .first()
+            .and_then(|x| x.strip_prefix("trusted comment: "))
+            .ok_or(SignError::InvalidSignature("missing trusted comment"))?;
+        let global_signature = rest
+            .get(1)
+            .and_then(|x| BASE64.decode(x.trim()).ok())
+            .and_then(|x| ed25519_compact::Signature::from_slice(&x).ok())
+            .ok_or(SignError::InvalidSignature("missing global signature"))?;
+        let mut global = signature_bytes.to_vec();
+        global.extend(trusted_comment.as_bytes());
+        self.key
+            .verify(global, &global_signature)
+            .map_err(|_| failed("trusted comment signature does not match"))?;
+
+        Ok(trusted_comment.to_string())
+    }
+}
+
+impl SignatureVerifier for MinisignPublicKey {
+    fn verify_file(&self, file: &Path, signature: &Path) -> Result<(), SignError> {
+        self.verify(file, &std::fs::read_to_string(signature)?)?;
+        Ok(())
+    }
+}
+
+/// Signs and verifies with the `gpg` binary and its keyring
+#[derive(Debug, Clone)]
+pub struct Gpg {
+    local_user: Option<String>,
+    homedir: Option<PathBuf>,
+    armor: bool,
+}
+
+impl Default for Gpg {
+    fn default() -> Self {
+        Self {
+            local_user: None,
+            homedir: None,
+            armor: true,
+        }
+    }
+}
+
+impl Gpg {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Key id, fingerprint or user id to sign with. Defaults to the gpg default key
+    pub fn with_local_user<S>(&mut self, key: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.local_user = Some(key.into());
+        self
+    }
+
+    /// Keyring directory instead of `~/.gnupg`
+    pub fn with_homedir<P>(&mut self, homedir: P) -> &mut Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.homedir = Some(homedir.into());
+        self
+    }
+
+    /// Binary `.sig` signatures instead of ASCII armored `.asc`
+    pub fn binary(&mut self) -> &mut Self {
+        self.armor = false;
+        self
+    }
+
+    fn homedir_args(&self) -> Vec<String> {
+        self.homedir
+            .iter()
+            .flat_map(|x| ["--homedir".to_string(), x.to_string_lossy().to_string()])
+            .collect()
+    }
+}
+
+impl Signer for Gpg {
+    fn extension(&self) -> &'static str {
+        if self.armor {
+            "asc"
+        } else {
+            "sig"
+        }
+    }
+
+    fn sign_file_to(&self, file: &Path, signature: &Path) -> Result<(), SignError> {
+        let sh = Shell::new()?;
+        let homedir = self.homedir_args();
+        let local_user = self
+            .local_user
+            .iter()
+            .flat_map(|x| ["--local-user".to_string(), x.clone()])
+            .collect::<Vec<_>>();
+        let armor = self.armor.then_some("--armor");
+
+        cmd!(
+            sh,
+            "gpg --batch --yes {homedir...} {local_user...} {armor...} --detach-sign --output {signature} {file}"
+        )
+        .quiet()
+        .run()?;
+        Ok(())
+    }
+}
+
+impl SignatureVerifier for Gpg {
+    fn verify_file(&self, file: &Path, signature: &Path) -> Result<(), SignError> {
+        let sh = Shell::new()?;
+        let homedir = self.homedir_args();
+
+        let output = cmd!(sh, "gpg --batch {homedir...} --verify {signature} {file}")
+            .quiet()
+            .ignore_status()
+            .output()?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(SignError::VerificationFailed(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    // Signatures of `test` from the minisign-verify test suite, made by minisign
+    const MINISIGN_PUBLIC_KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+    const MINISIGN_LEGACY_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RWQf6LRCGA9i59SLOFxz6NxvASXDJeRtuZykwQepbDEGt87ig1BNpWaVWuNrm73YiIiJbq71Wi+dP9eKL8OC351vwIasSSbXxwA=
+trusted comment: timestamp:1555779966\tfile:test
+QtKMXWyYcwdpZAlPF7tE2ENJkRd1ujvKjlj1m9RtHTBnZPa5WKU5uWRs5GoP5M/VqE81QFuMKI5k/SfNQUaOAA==
+";
+    const MINISIGN_PREHASHED_SIGNATURE: &str =
+        "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==
+";
+
+    // Keys and signatures of `release contents\n` created with libsodium the way minisign does
+    const UNENCRYPTED_SECRET_KEY: &str = "untrusted comment: minisign encrypted secret key
+RWQAAEIyAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAASNFZ4mrze+hFsntRtYgdzSkMxfTD9iPUqyGNMN9kEu/TkHYZfkEdTvEPtls0uWjr83+FNA5Fewzsbp6YNcsoWGlZJa2pOe+nvtbAF8mG+GbHehgr94O2Dr21UXvCkTY2Fsf3Bn41Is=
+";
+    const UNENCRYPTED_PUBLIC_KEY: &str = "untrusted comment: minisign public key EFCDAB8967452301
+RWQBI0VniavN7zvEPtls0uWjr83+FNA5Fewzsbp6YNcsoWGlZJa2pOe+
+";
+    const UNENCRYPTED_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQBI0VniavN72b6zoNT6o0rQL6vvq1cac88vCyt0kJDqUabg6rZ7CSslATz0J+Ypu4b9SrftaMcK0sv8MQ1twkKqqNEC7Adrg4=
+trusted comment: timestamp:1700000000\tfile:SHA256SUMS\thashed
+/k/ecvhISIqB7Jdg5us8gRdqhAyyeZh9T3QaDNRVkPdGeGKbGlC3ioGPmR3niEjxkqESnJqGZdezaKB9lZAhBQ==
+";
+    const ENCRYPTED_SECRET_KEY: &str = "untrusted comment: minisign encrypted secret key
+RWRTY0IyY0ea1poJCyWCd+yPum+ZQZov+ySJgVEGV8lEzNEUjpcAAAEAAAAAAAAAAAEAAAAA2rnuqV+S1vA+n1XivzP5lDst4HmXhpV4Xyoq6Q2xh5/VboN44cwnM0FyZhNc27Jl76gvhvGI47hx8UvTN08dY+ITPUDrGSF3yQ0BazZ+5DJ5qcaIoLF6XyMKOyTeLTFKGjhg+D55axc=
+";
+    const ENCRYPTED_PUBLIC_KEY: &str = "untrusted comment: minisign public key 1032547698BADCFE
+RWT+3LqYdlQyEG/E9uhQ0QxraLzeXpmalJFaYmPWDdeKOWCmDcrZzy1Y
+";
+    const ENCRYPTED_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUT+3LqYdlQyEAC8hovlrBb7Hfe18gWwgWK+mWqa7JZkoN2vk9qQ5BeOyKfYmzBX/WBBmnNkQIBe4TvXCMqt+AWheEfeDAptuQ0=
+trusted comment: timestamp:1700000000\tfile:SHA256SUMS\thashed
+Kz0Il4wsE1Ejhagjy7f3rTObv45+HSYBqswEOjnF3oidocnOipB+n2PibjdzZokCPVpTVDcNKMp9sdLTn/+HCQ==
+";
+    const TRUSTED_COMMENT: &str = "timestamp:1700000000\tfile:SHA256SUMS\thashed";
+
+    fn sign(key: MinisignSecretKey, file: &Path) -> String {
+        MinisignSigner::new(key)
+            .with_trusted_comment(TRUSTED_COMMENT)
+            .sign(file)
+            .unwrap()
+    }
+
+    #[test]
+    fn verifies_minisign_signatures() {
+        let dir = TempDir::new();
+        let file = dir.write("test", "test");
+        let key = MinisignPublicKey::decode(MINISIGN_PUBLIC_KEY).unwrap();
+        assert_eq!(key.key_id(), "E7620F1842B4E81F");
+
+        for signature in [MINISIGN_LEGACY_SIGNATURE, MINISIGN_PREHASHED_SIGNATURE] {
+            assert!(key.verify(&file, signature).is_ok());
+        }
+        assert_eq!(
+            key.verify(&file, MINISIGN_PREHASHED_SIGNATURE).unwrap(),
+            "timestamp:1556193335\tfile:test"
+        );
+
+        let file = dir.write("test", "Test");
+        for signature in [MINISIGN_LEGACY_SIGNATURE, MINISIGN_PREHASHED_SIGNATURE] {
+            assert!(matches!(
+                key.verify(&file, signature),
+                Err(SignError::VerificationFailed(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn decodes_unencrypted_key() {
+        let dir = TempDir::new();
+        let file = dir.write("SHA256SUMS", "release contents\n");
+        let key = MinisignSecretKey::decode(UNENCRYPTED_SECRET_KEY, None).unwrap();
+
+        assert_eq!(key.key_id(), "EFCDAB8967452301");
+        assert_eq!(key.public_key(), UNENCRYPTED_PUBLIC_KEY);
+        assert_eq!(key.encode_unencrypted(), UNENCRYPTED_SECRET_KEY);
+        assert_eq!(sign(key, &file), UNENCRYPTED_SIGNATURE);
+
+        let public_key = MinisignPublicKey::decode(UNENCRYPTED_PUBLIC_KEY).unwrap();
+        assert_eq!(
+            public_key.verify(&file, UNENCRYPTED_SIGNATURE).unwrap(),
+            TRUSTED_COMMENT
+        );
+    }
+
+    #[test]
+    fn decodes_encrypted_key() {
+        let dir = TempDir::new();
+        let file = dir.write("SHA256SUMS", "release contents\n");
+        let key = MinisignSecretKey::decode(ENCRYPTED_SECRET_KEY, Some("correct horse")).unwrap();
+
+        assert_eq!(key.key_id(), "1032547698BADCFE");
+        assert_eq!(key.public_key(), ENCRYPTED_PUBLIC_KEY);
+        assert_eq!(sign(key, &file), ENCRYPTED_SIGNATURE);
+
+        let public_key = MinisignPublicKey::decode(ENCRYPTED_PUBLIC_KEY).unwrap();
+        assert_eq!(
+            public_key.verify(&file, ENCRYPTED_SIGNATURE).unwrap(),
+            TRUSTED_COMMENT
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_password() {
+        assert!(matches!(
+            MinisignSecretKey::decode(ENCRYPTED_SECRET_KEY, None),
+            Err(SignError::PasswordRequired)
+        ));
+        assert!(matches!(
+            MinisignSecretKey::decode(ENCRYPTED_SECRET_KEY, Some("wrong horse")),
+            Err(SignError::WrongPassword)
+        ));
+    }
+
+    #[test]
+    fn signs_and_verifies() {
+        let dir = TempDir::new();
+        let file = dir.write("app.tar.gz", "archive contents");
+        let key = MinisignSecretKey::from_seed([7; KEY_ID_BYTES], [42; 32]).unwrap();
+        let public_key = MinisignPublicKey::decode(&key.public_key()).unwrap();
+
+        let signer = MinisignSigner::new(key);
+        let signature = signer.sign_file(&file).unwrap();
+        assert_eq!(signature, dir.join("app.tar.gz.minisig"));
+        assert!(public_key.verify_file(&file, &signature).is_ok());
+
+        let comment = public_key
+            .verify(&file, &std::fs::read_to_string(&signature).unwrap())
+            .unwrap();
+        assert!(comment.starts_with("timestamp:"));
+        assert!(comment.ends_with("\tfile:app.tar.gz\thashed"));
+    }
+
+    #[test]
+    fn rejects_signature_of_other_key() {
+        let dir = TempDir::new();
+        let file = dir.write("SHA256SUMS", "release contents\n");
+        let public_key = MinisignPublicKey::decode(ENCRYPTED_PUBLIC_KEY).unwrap();
+
+        match public_key.verify(&file, UNENCRYPTED_SIGNATURE) {
+            Err(SignError::KeyMismatch { key, signature_key }) => {
+                assert_eq!(key, "1032547698BADCFE");
+                assert_eq!(signature_key, "EFCDAB8967452301");
+            }
+            x => panic!("unexpected result {x:?}"),
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_trusted_comment() {
+        let dir = TempDir::new();
+        let file = dir.write("SHA256SUMS", "release contents\n");
+        let public_key = MinisignPublicKey::decode(UNENCRYPTED_PUBLIC_KEY).unwrap();
+        let signature =
+            UNENCRYPTED_SIGNATURE.replace("timestamp:1700000000", "timestamp:1800000000");
+
+        assert!(matches!(
+            public_key.verify(&file, &signature),
+            Err(SignError::VerificationFailed(x)) if x.contains("trusted comment")
+        ));
+    }
+
+    #[test]
+    fn rejects_tampered_file() {
+        let dir = TempDir::new();
+        let file = dir.write("SHA256SUMS", "release contents, changed\n");
+        let public_key = MinisignPublicKey::decode(UNENCRYPTED_PUBLIC_KEY).unwrap();
+
+        assert!(matches!(
+            public_key.verify(&file, UNENCRYPTED_SIGNATURE),
+            Err(SignError::VerificationFailed(x)) if x.contains("file signature")
+        ));
+    }
+}
@@ -0,0 +1,82 @@
+use std::path::{Path, PathBuf};
+
+use super::{read, relative_path, Checksum, Filter, HashAlgorithm, PathChecksum};
+use crate::filter::into_io_error;
+
+/// First line of the serialized tree. Bumped whenever the format changes
+const TREE_HEADER: &[u8] = b"xtask-toolkit-tree-v1\n";
+
+enum TreeEntry {
+    Directory,
+    File { executable: bool, path: PathBuf },
+    Symlink(PathBuf),
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    false
+}
+
+/// Digest of the serialized tree. Every entry is written as one of
+///
+/// - `d\0<path>\n` for directories (so empty directories count),
+/// - `f\0<x|->\0<path>\0<content digest>\n` for regular files,
+/// - `l\0<path>\0<target>\n` for symlinks, which are never followed,
+///
+/// in the walk order, which is sorted by file name on every level. The root itself has an
+/// empty path, so renaming the root directory does not change the digest
+pub(crate) fn tree_checksum(
+    root: &Path,
+    algorithm: HashAlgorithm,
+    filter: &Filter,
+) -> Result<Checksum, std::io::Error> {
+    let mut entries = Vec::new();
+    for entry in filter.walk(root)? {
+        let Some(file_type) = entry.file_type() else {
+            continue;
+        };
+        let name = relative_path(root, entry.path());
+
+        let tree_entry = if file_type.is_symlink() {
+            TreeEntry::Symlink(std::fs::read_link(entry.path())?)
+        } else if file_type.is_dir() {
+            TreeEntry::Directory
+        } else {
+            TreeEntry::File {
+                executable: is_executable(&entry.metadata().map_err(into_io_error)?),
+                path: entry.into_path(),
+            }
+        };
+        entries.push((name, tree_entry));
+    }
+
+    let contents = read::map_ordered(&entries, |(_, entry)| match entry {
+        TreeEntry::File { path, .. } => path.calculate(algorithm).map(Some),
+        _ => Ok(None),
+    });
+
+    let mut hasher = algorithm.hasher();
+    hasher.update(TREE_HEADER);
+    for ((name, entry), content) in entries.iter().zip(contents) {
+        let line = match (entry, content?) {
+            (TreeEntry::Directory, _) => format!("d\0{name}\n"),
+            (TreeEntry::Symlink(target), _) => {
+                format!("l\0{name}\0{}\n", target.to_string_lossy())
+            }
+            (TreeEntry::File { executable, .. }, content) => format!(
+                "f\0{}\0{name}\0{}\n",
+                if *executable { "x" } else { "-" },
+                content.as_ref().map(Checksum::get).unwrap_or_default()
+            ),
+        };
+        hasher.update(line.as_bytes());
+    }
+
+    Ok(Checksum::from_bytes(algorithm, &hasher.finalize()))
+}
@@ -0,0 +1,180 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+
+type Predicate = Arc<dyn Fn(&Path) -> bool + Send + Sync>;
+
+/// Selects the entries taken into account when walking a directory
+///
+/// By default everything is included. Every configured rule has to accept an entry, and a
+/// rejected directory is skipped together with its contents:
+///
+/// - with [`Filter::git_ignore`], paths ignored by `.gitignore`, `.ignore`, `.git/info/exclude`
+///   and the global git excludes are skipped, as is the `.git` directory itself,
+/// - [`Filter::exclude`] globs reject matching paths,
+/// - once any [`Filter::include`] glob or [`Filter::include_if`] closure is given, only files
+///   accepted by one of them are taken (directories are still descended into),
+/// - [`Filter::predicate`] closures receive the full path and reject by returning `false`.
+///
+/// Globs use the gitignore syntax and are relative to the walked directory
+#[derive(Clone, Default)]
+pub struct Filter {
+    git_ignore: bool,
+    include: Vec<String>,
+    include_predicates: Vec<Predicate>,
+    exclude: Vec<String>,
+    predicates: Vec<Predicate>,
+}
+
+impl std::fmt::Debug for Filter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Filter")
+            .field("git_ignore", &self.git_ignore)
+            .field("include", &self.include)
+            .field("include_predicates", &self.include_predicates.len())
+            .field("exclude", &self.exclude)
+            .field("predicates", &self.predicates.len())
+            .finish()
+    }
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The source tree as git sees it. Ignore files apply even outside of a git repository
+    pub fn git_ignore(mut self) -> Self {
+        self.git_ignore = true;
+        self
+    }
+
+    pub fn include<S>(mut self, glob: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.include.push(glob.into());
+        self
+    }
+
+    /// Take the files the closure returns `true` for, in addition to the [`Filter::include`] globs
+    pub fn include_if<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&Path) -> bool + Send + Sync + 'static,
+    {
+        self.include_predicates.push(Arc::new(predicate));
+        self
+    }
+
+    pub fn exclude<S>(mut self, glob: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.exclude.push(glob.into());
+        self
+    }
+
+    pub fn predicate<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&Path) -> bool + Send + Sync + 'static,
+    {
+        self.predicates.push(Arc::new(predicate));
+        self
+    }
+
+    /// Whether any [`Filter::include`] glob is set. Globs can match below the top level
+    #[cfg(feature = "targz")]
+    pub(crate) fn has_include_globs(&self) -> bool {
+        !self.include.is_empty()
+    }
+
+    /// Entries below `root` accepted by the filter (the root included), sorted by file name on
+    /// every level. Symlinks are not followed
+    pub(crate) fn walk(&self, root: &Path) -> Result<Vec<ignore::DirEntry>, std::io::Error> {
+        let mut include = OverrideBuilder::new(root);
+        for glob in &self.include {
+            include.add(glob).map_err(into_io_error)?;
+        }
+        let include = include.build().map_err(into_io_error)?;
+
+        let mut overrides = OverrideBuilder::new(root);
+        for glob in &self.exclude {
+            overrides.add(&format!("!{glob}")).map_err(into_io_error)?;
+        }
+
+        let predicates = self.predicates.clone();
+        let git_ignore = self.git_ignore;
+        let mut walker = WalkBuilder::new(root);
+        walker
+            .standard_filters(false)
+            .git_ignore(git_ignore)
+            .git_global(git_ignore)
+            .git_exclude(git_ignore)
+            .ignore(git_ignore)
+            .parents(git_ignore)
+            .require_git(false)
+            .overrides(overrides.build().map_err(into_io_error)?)
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .filter_entry(move |entry| {
+                entry.depth() == 0
+                    || (!(git_ignore && entry.file_name() == ".git")
+                        && predicates.iter().all(|predicate| predicate(entry.path())))
+            });
+
+        // include rules are OR-ed, so they are applied to the files after the walk
+        let include_all = self.include.is_empty() && self.include_predicates.is_empty();
+        let is_included = |entry: &ignore::DirEntry| {
+            include_all
+                || entry.depth() == 0
+                || entry.file_type().is_some_and(|x| x.is_dir())
+                || include.matched(entry.path(), false).is_whitelist()
+                || self
+                    .include_predicates
+                    .iter()
+                    .any(|predicate| predicate(entry.path()))
+        };
+
+        let mut entries = Vec::new();
+        for entry in walker.build() {
+            let entry = entry.map_err(into_io_error)?;
+            if is_included(&entry) {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Regular files accepted by the filter, in the walk order
+    #[cfg(feature = "checksums")]
+    pub(crate) fn walk_files(
+        &self,
+        root: &Path,
+    ) -> Result<Vec<std::path::PathBuf>, std::io::Error> {
+        Ok(self
+            .walk(root)?
+            .into_iter()
+            .filter(|entry| entry.file_type().is_some_and(|x| x.is_file()))
+            .map(|entry| entry.into_path())
+            .collect())
+    }
+}
+
+/// Same as [`Filter::include_if`]
+impl<F> From<F> for Filter
+where
+    F: Fn(&Path) -> bool + Send + Sync + 'static,
+{
+    fn from(predicate: F) -> Self {
+        Self::new().include_if(predicate)
+    }
+}
+
+/// Invalid globs are reported as [`std::io::ErrorKind::InvalidInput`]
+pub(crate) fn into_io_error(error: ignore::Error) -> std::io::Error {
+    let kind = error
+        .io_error()
+        .map_or(std::io::ErrorKind::InvalidInput, |x| x.kind());
+    std::io::Error::new(kind, error)
+}
@@ -0,0 +1,158 @@
+use std::path::PathBuf;
+
+use xshell::{cmd, Shell};
+
+use super::{ForgeError, ForgeRelease, NewRelease, ReleaseBackend, RemoteRepository};
+
+/// Release as returned by the REST API, `gh release list` has no pagination
+#[derive(serde::Deserialize)]
+struct GhListedRelease {
+    tag_name: String,
+    name: Option<String>,
+    html_url: Option<String>,
+
+    #[serde(default)]
+    draft: bool,
+
+    #[serde(default)]
+    prerelease: bool,
+
+    #[serde(default)]
+    assets: Vec<GhAsset>,
+}
+
+#[derive(serde::Deserialize)]
+struct GhAsset {
+    name: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GhViewedRelease {
+    tag_name: String,
+    name: Option<String>,
+    url: String,
+
+    #[serde(default)]
+    is_draft: bool,
+
+    #[serde(default)]
+    is_prerelease: bool,
+
+    #[serde(default)]
+    assets: Vec<GhAsset>,
+}
+
+impl From<GhViewedRelease> for ForgeRelease {
+    fn from(value: GhViewedRelease) -> Self {
+        ForgeRelease {
+            tag: value.tag_name,
+            name: value.name.filter(|x| !x.is_empty()),
+            url: Some(value.url),
+            draft: value.is_draft,
+            prerelease: value.is_prerelease,
+            assets: value.assets.into_iter().map(|x| x.name).collect(),
+        }
+    }
+}
+
+/// GitHub backend driving the `gh` binary
+pub struct GhCliBackend {
+    host: String,
+    path: String,
+    repo: String,
+}
+
+impl GhCliBackend {
+    pub fn new(remote: &RemoteRepository) -> Self {
+        Self {
+            host: remote.host.clone(),
+            path: remote.path.clone(),
+            repo: format!("{}/{}", remote.host, remote.path),
+        }
+    }
+
+    fn view(&self, tag: &str) -> Result<ForgeRelease, ForgeError> {
+        let sh = Shell::new()?;
+        let repo = &self.repo;
+        let release: GhViewedRelease = serde_json::from_str(
+            &cmd!(
+                sh,
+                "gh release view {tag} -R {repo} --json tagName,name,url,isDraft,isPrerelease,assets"
+            )
+            .read()?,
+        )?;
+        Ok(release.into())
+    }
+}
+
+impl ReleaseBackend for GhCliBackend {
+    fn list_releases(&self) -> Result<Vec<ForgeRelease>, ForgeError> {
+        let sh = Shell::new()?;
+        let host = &self.host;
+        let endpoint = format!("repos/{}/releases?per_page=100", self.path);
+        // every page is printed as its own JSON array
+        let output = cmd!(sh, "gh api --hostname {host} --paginate {endpoint}").read()?;
+
+        let mut releases = Vec::new();
+        for page in serde_json::Deserializer::from_str(&output).into_iter::<Vec<GhListedRelease>>()
+        {
+            releases.extend(page?.into_iter().map(|x| ForgeRelease {
+                tag: x.tag_name,
+                name: x.name.filter(|x| !x.is_empty()),
+                url: x.html_url,
+                draft: x.draft,
+                prerelease: x.prerelease,
+                assets: x.assets.into_iter().map(|x| x.name).collect(),
+            }));
+        }
+        Ok(releases)
+    }
+
+    fn get_release(&self, tag: &str) -> Result<ForgeRelease, ForgeError> {
+        self.view(tag)
+    }
+
+    fn create_release(&self, release: &NewRelease) -> Result<ForgeRelease, ForgeError> {
+        let sh = Shell::new()?;
+        let repo = &self.repo;
+        let tag = &release.tag;
+
+        let mut args = Vec::new();
+        match &release.notes {
+            Some(notes) => args.extend(["--notes".to_string(), notes.clone()]),
+            None => args.push("--generate-notes".to_string()),
+        }
+        if let Some(title) = &release.title {
+            args.extend(["--title".to_string(), title.clone()]);
+        }
+        if let Some(target) = &release.target {
+            args.extend(["--target".to_string(), target.clone()]);
+        }
+        if release.draft {
+            args.push("--draft".to_string());
+        }
+        if release.prerelease {
+            args.push("--prerelease".to_string());
+        }
+
+        cmd!(sh, "gh release create {tag} -R {repo} {args...}").read()?;
+        self.view(tag)
+    }
+
+    fn upload_assets(&self, tag: &str, files: &[PathBuf]) -> Result<(), ForgeError> {
+        let sh = Shell::new()?;
+        let repo = &self.repo;
+
+        cmd!(sh, "gh release upload {tag} -R {repo} --clobber {files...}").read()?;
+        Ok(())
+    }
+
+    fn delete_release(&self, tag: &str) -> Result<(), ForgeError> {
+        let sh = Shell::new()?;
+        let repo = &self.repo;
+
+        cmd!(sh, "gh release delete {tag} -R {repo} --yes").read()?;
+        Ok(())
+    }
+}
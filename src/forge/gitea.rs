@@ -0,0 +1,253 @@
+use std::path::PathBuf;
+
+use super::http::{encode, file_name, Api};
+use super::{ForgeError, ForgeRelease, NewRelease, ReleaseBackend, RemoteRepository};
+
+pub const GITEA_TOKEN: &str = "GITEA_TOKEN";
+
+const PAGE_SIZE: usize = 50;
+
+#[derive(serde::Deserialize)]
+struct GiteaAsset {
+    id: u64,
+    name: String,
+}
+
+#[derive(serde::Deserialize)]
+struct GiteaRelease {
+    id: u64,
+    tag_name: String,
+    name: Option<String>,
+    html_url: Option<String>,
+
+    #[serde(default)]
+    draft: bool,
+
+    #[serde(default)]
+    prerelease: bool,
+
+    #[serde(default)]
+    assets: Vec<GiteaAsset>,
+}
+
+impl From<GiteaRelease> for ForgeRelease {
+    fn from(value: GiteaRelease) -> Self {
+        ForgeRelease {
+            tag: value.tag_name,
+            name: value.name.filter(|x| !x.is_empty()),
+            url: value.html_url,
+            draft: value.draft,
+            prerelease: value.prerelease,
+            assets: value.assets.into_iter().map(|x| x.name).collect(),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct GiteaNewRelease<'a> {
+    tag_name: &'a str,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_commitish: Option<&'a str>,
+
+    draft: bool,
+    prerelease: bool,
+}
+
+/// Gitea and Forgejo REST (v1) backend
+pub struct GiteaBackend {
+    remote: RemoteRepository,
+    token: String,
+    api: Api,
+}
+
+impl GiteaBackend {
+    pub fn new(remote: &RemoteRepository, token: &str) -> Self {
+        Self {
+            remote: remote.clone(),
+            token: token.to_string(),
+            api: Api::new(
+                &format!("{}/api/v1", remote.base_url),
+                Some(("Authorization", format!("token {token}"))),
+            ),
+        }
+    }
+
+    /// Token from `GITEA_TOKEN`
+    pub fn from_env(remote: &RemoteRepository) -> Result<Self, ForgeError> {
+        let token =
+            std::env::var(GITEA_TOKEN).map_err(|_| ForgeError::MissingToken(GITEA_TOKEN))?;
+        Ok(Self::new(remote, &token))
+    }
+
+    /// Use a different api root than `<remote>/api/v1`
+    pub fn with_api_url(mut self, url: &str) -> Self {
+        self.api = Api::new(
+            url,
+            Some(("Authorization", format!("token {}", self.token))),
+        );
+        self
+    }
+
+    fn repo(&self) -> String {
+        let (owner, name) = self.remote.owner_and_name();
+        format!("/repos/{}/{}", encode(owner), encode(name))
+    }
+
+    fn release(&self, tag: &str) -> Result<GiteaRelease, ForgeError> {
+        self.api
+            .get_json(
+                &format!("{}/releases/tags/{}", self.repo(), encode(tag)),
+                &[],
+            )
+            .map_err(|error| match error {
+                ForgeError::HttpStatus { status: 404, .. } => {
+                    ForgeError::ReleaseNotFound(tag.to_string())
+                }
+                error => error,
+            })
+    }
+}
+
+impl ReleaseBackend for GiteaBackend {
+    fn list_releases(&self) -> Result<Vec<ForgeRelease>, ForgeError> {
+        let path = format!("{}/releases", self.repo());
+        let mut result = Vec::new();
+
+        for page in 1.. {
+            let releases: Vec<GiteaRelease> = self.api.get_json(
+                &path,
+                &[("limit", PAGE_SIZE.to_string()), ("page", page.to_string())],
+            )?;
+            if releases.is_empty() {
+                break;
+            }
+            result.extend(releases.into_iter().map(ForgeRelease::from));
+        }
+
+        Ok(result)
+    }
+
+    fn get_release(&self, tag: &str) -> Result<ForgeRelease, ForgeError> {
+        Ok(self.release(tag)?.into())
+    }
+
+    fn create_release(&self, release: &NewRelease) -> Result<ForgeRelease, ForgeError> {
+        let created: GiteaRelease = self.api.post_json(
+            &format!("{}/releases", self.repo()),
+            &GiteaNewRelease {
+                tag_name: &release.tag,
+                name: release.title.as_deref(),
+                body: release.notes.as_deref(),
+                target_commitish: release.target.as_deref(),
+                draft: release.draft,
+                prerelease: release.prerelease,
+            },
+        )?;
+        Ok(created.into())
+    }
+
+    fn upload_assets(&self, tag: &str, files: &[PathBuf]) -> Result<(), ForgeError> {
+        let release = self.release(tag)?;
+        let assets_path = format!("{}/releases/{}/assets", self.repo(), release.id);
+
+        for file in files {
+            let name = file_name(file)?;
+
+            for asset in release.assets.iter().filter(|x| x.name == name) {
+                self.api.delete(&format!("{assets_path}/{}", asset.id))?;
+            }
+
+            let _: GiteaAsset = self.api.post_file(
+                &format!("{assets_path}?name={}", encode(&name)),
+                "attachment",
+                file,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn delete_release(&self, tag: &str) -> Result<(), ForgeError> {
+        self.api
+            .delete(&format!("{}/releases/tags/{}", self.repo(), encode(tag)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::stub::{Response, StubServer};
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    fn backend(server: &StubServer) -> GiteaBackend {
+        let remote = RemoteRepository::parse("https://codeberg.org/owner/repo.git").unwrap();
+        GiteaBackend::new(&remote, "secret").with_api_url(&server.url)
+    }
+
+    #[test]
+    fn list_releases_until_empty_page() {
+        let server = StubServer::start(|request, _| {
+            assert_eq!(request.path(), "/repos/owner/repo/releases");
+            assert_eq!(request.header("authorization"), Some("token secret"));
+            match request.query("page") {
+                Some("1") => Response::json(
+                    200,
+                    r#"[{"id": 1, "tag_name": "v2"}, {"id": 2, "tag_name": "v1", "prerelease": true}]"#,
+                ),
+                Some("2") => Response::json(200, r#"[{"id": 3, "tag_name": "v0"}]"#),
+                _ => Response::json(200, "[]"),
+            }
+        });
+
+        let releases = backend(&server).list_releases().unwrap();
+        let tags: Vec<_> = releases.iter().map(|x| x.tag.as_str()).collect();
+        assert_eq!(tags, ["v2", "v1", "v0"]);
+        assert!(releases[1].prerelease);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn upload_replaces_existing_asset() {
+        let server = StubServer::start(|request, _| match request.method.as_str() {
+            "GET" => Response::json(
+                200,
+                r#"{"id": 7, "tag_name": "v1", "assets": [{"id": 3, "name": "app.tar.gz"}, {"id": 4, "name": "other"}]}"#,
+            ),
+            "DELETE" => Response::json(204, ""),
+            _ => Response::json(201, r#"{"id": 5, "name": "app.tar.gz"}"#),
+        });
+        let dir = TempDir::new();
+        let file = dir.write("app.tar.gz", "archive contents");
+
+        backend(&server).upload_assets("v1", &[file]).unwrap();
+
+        let requests = server.requests();
+        let calls: Vec<_> = requests
+            .iter()
+            .map(|x| format!("{} {}", x.method, x.target))
+            .collect();
+        assert_eq!(
+            calls,
+            [
+                "GET /repos/owner/repo/releases/tags/v1",
+                "DELETE /repos/owner/repo/releases/7/assets/3",
+                "POST /repos/owner/repo/releases/7/assets?name=app.tar.gz",
+            ]
+        );
+
+        let upload = &requests[2];
+        assert!(upload
+            .header("content-type")
+            .unwrap()
+            .starts_with("multipart/form-data; boundary="));
+        let body = String::from_utf8_lossy(&upload.body);
+        assert!(body.contains("name=\"attachment\"; filename=\"app.tar.gz\""));
+        assert!(body.contains("\r\n\r\narchive contents\r\n--"));
+    }
+}
@@ -0,0 +1,327 @@
+use std::path::PathBuf;
+
+use super::http::{encode, file_name, Api};
+use super::{ForgeError, ForgeRelease, NewRelease, ReleaseBackend, RemoteRepository};
+
+pub const GITHUB_TOKEN: &str = "GITHUB_TOKEN";
+pub const GH_TOKEN: &str = "GH_TOKEN";
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+
+#[derive(serde::Deserialize)]
+struct GitHubAsset {
+    id: u64,
+    name: String,
+}
+
+#[derive(serde::Deserialize)]
+struct GitHubRelease {
+    id: u64,
+    tag_name: String,
+    name: Option<String>,
+    html_url: Option<String>,
+    upload_url: String,
+
+    #[serde(default)]
+    draft: bool,
+
+    #[serde(default)]
+    prerelease: bool,
+
+    #[serde(default)]
+    assets: Vec<GitHubAsset>,
+}
+
+impl From<GitHubRelease> for ForgeRelease {
+    fn from(value: GitHubRelease) -> Self {
+        ForgeRelease {
+            tag: value.tag_name,
+            name: value.name.filter(|x| !x.is_empty()),
+            url: value.html_url,
+            draft: value.draft,
+            prerelease: value.prerelease,
+            assets: value.assets.into_iter().map(|x| x.name).collect(),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct GitHubNewRelease<'a> {
+    tag_name: &'a str,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_commitish: Option<&'a str>,
+
+    draft: bool,
+    prerelease: bool,
+    generate_release_notes: bool,
+}
+
+/// GitHub REST backend. Does not need the `gh` binary
+///
+/// Works with GitHub Enterprise (`https://<host>/api/v3`) and stub servers through [`GitHubBackend::with_api_url`]
+pub struct GitHubBackend {
+    remote: RemoteRepository,
+    token: String,
+    api: Api,
+}
+
+impl GitHubBackend {
+    pub fn new(remote: &RemoteRepository, token: &str) -> Self {
+        let api_url = if remote.host == "github.com" {
+            GITHUB_API_URL.to_string()
+        } else {
+            format!("{}/api/v3", remote.base_url)
+        };
+
+        Self {
+            remote: remote.clone(),
+            token: token.to_string(),
+            api: Self::api(&api_url, token),
+        }
+    }
+
+    /// Token from `GITHUB_TOKEN` or `GH_TOKEN`
+    pub fn from_env(remote: &RemoteRepository) -> Result<Self, ForgeError> {
+        let token = std::env::var(GITHUB_TOKEN)
+            .or_else(|_| std::env::var(GH_TOKEN))
+            .map_err(|_| ForgeError::MissingToken(GITHUB_TOKEN))?;
+        Ok(Self::new(remote, &token))
+    }
+
+    /// Use a different api root than `https://api.github.com`
+    pub fn with_api_url(mut self, url: &str) -> Self {
+        self.api = Self::api(url, &self.token);
+        self
+    }
+
+    fn api(url: &str, token: &str) -> Api {
+        Api::new(url, Some(("Authorization", format!("Bearer {token}"))))
+    }
+
+    fn repo(&self) -> String {
+        let (owner, name) = self.remote.owner_and_name();
+        format!("/repos/{}/{}", encode(owner), encode(name))
+    }
+
+    fn releases(&self) -> Result<Vec<GitHubRelease>, ForgeError> {
+        let mut result = Vec::new();
+        let mut next = Some(format!("{}/releases?per_page=100", self.repo()));
+
+        while let Some(url) = next {
+            let mut response = self.api.get(&url, &[])?;
+            next = response
+                .headers()
+                .get("link")
+                .and_then(|x| x.to_str().ok())
+                .and_then(next_page);
+
+            let body = response
+                .body_mut()
+                .read_to_string()
+                .map_err(|error| ForgeError::HttpError(Box::new(error)))?;
+            result.extend(serde_json::from_str::<Vec<GitHubRelease>>(&body)?);
+        }
+
+        Ok(result)
+    }
+
+    fn release(&self, tag: &str) -> Result<GitHubRelease, ForgeError> {
+        match self.api.get_json(
+            &format!("{}/releases/tags/{}", self.repo(), encode(tag)),
+            &[],
+        ) {
+            // drafts are not reachable by tag
+            Err(ForgeError::HttpStatus { status: 404, .. }) => self
+                .releases()?
+                .into_iter()
+                .find(|x| x.tag_name == tag)
+                .ok_or_else(|| ForgeError::ReleaseNotFound(tag.to_string())),
+            result => result,
+        }
+    }
+}
+
+// `Link: <https://...&page=2>; rel="next", <https://...>; rel="last"`
+fn next_page(link: &str) -> Option<String> {
+    link.split(',').find_map(|part| {
+        let (url, rel) = part.split_once(';')?;
+        rel.contains("rel=\"next\"").then(|| {
+            url.trim()
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .to_string()
+        })
+    })
+}
+
+impl ReleaseBackend for GitHubBackend {
+    fn list_releases(&self) -> Result<Vec<ForgeRelease>, ForgeError> {
+        Ok(self
+            .releases()?
+            .into_iter()
+            .map(ForgeRelease::from)
+            .collect())
+    }
+
+    fn get_release(&self, tag: &str) -> Result<ForgeRelease, ForgeError> {
+        Ok(self.release(tag)?.into())
+    }
+
+    fn create_release(&self, release: &NewRelease) -> Result<ForgeRelease, ForgeError> {
+        let created: GitHubRelease = self.api.post_json(
+            &format!("{}/releases", self.repo()),
+            &GitHubNewRelease {
+                tag_name: &release.tag,
+                name: release.title.as_deref(),
+                body: release.notes.as_deref(),
+                target_commitish: release.target.as_deref(),
+                draft: release.draft,
+                prerelease: release.prerelease,
+                generate_release_notes: release.notes.is_none(),
+            },
+        )?;
+        Ok(created.into())
+    }
+
+    fn upload_assets(&self, tag: &str, files: &[PathBuf]) -> Result<(), ForgeError> {
+        let release = self.release(tag)?;
+        // `https://uploads.github.com/repos/o/r/releases/1/assets{?name,label}`
+        let upload_url = release
+            .upload_url
+            .split_once('{')
+            .map_or(release.upload_url.as_str(), |x| x.0);
+
+        for file in files {
+            let name = file_name(file)?;
+
+            for asset in release.assets.iter().filter(|x| x.name == name) {
+                self.api
+                    .delete(&format!("{}/releases/assets/{}", self.repo(), asset.id))?;
+            }
+
+            let contents = std::fs::File::open(file)?;
+            let _: GitHubAsset = self.api.post_stream(
+                &format!("{upload_url}?name={}", encode(&name)),
+                "application/octet-stream",
+                contents.metadata()?.len(),
+                contents,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn delete_release(&self, tag: &str) -> Result<(), ForgeError> {
+        let release = self.release(tag)?;
+        self.api
+            .delete(&format!("{}/releases/{}", self.repo(), release.id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::stub::{Response, StubServer};
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    fn backend(server: &StubServer) -> GitHubBackend {
+        let remote = RemoteRepository::parse("git@github.com:owner/repo.git").unwrap();
+        GitHubBackend::new(&remote, "secret").with_api_url(&server.url)
+    }
+
+    fn release(id: u64, tag: &str, draft: bool, url: &str) -> String {
+        format!(
+            r#"{{"id": {id}, "tag_name": "{tag}", "draft": {draft}, "upload_url": "{url}/uploads/repos/owner/repo/releases/{id}/assets{{?name,label}}", "assets": [{{"id": 3, "name": "app.tar.gz"}}]}}"#
+        )
+    }
+
+    #[test]
+    fn list_releases_follows_link_header() {
+        let server = StubServer::start(|request, url| {
+            assert_eq!(request.header("authorization"), Some("Bearer secret"));
+            match request.query("page") {
+                None => Response::json(200, format!("[{}]", release(2, "v2", false, url)))
+                    .with_header(
+                        "Link",
+                        format!(
+                            r#"<{url}/repos/owner/repo/releases?per_page=100&page=2>; rel="next", <{url}/repos/owner/repo/releases?per_page=100&page=2>; rel="last""#
+                        ),
+                    ),
+                _ => Response::json(200, format!("[{}]", release(1, "v1", false, url))),
+            }
+        });
+
+        let releases = backend(&server).list_releases().unwrap();
+        let tags: Vec<_> = releases.iter().map(|x| x.tag.as_str()).collect();
+        assert_eq!(tags, ["v2", "v1"]);
+
+        let targets: Vec<_> = server.requests().into_iter().map(|x| x.target).collect();
+        assert_eq!(
+            targets,
+            [
+                "/repos/owner/repo/releases?per_page=100",
+                "/repos/owner/repo/releases?per_page=100&page=2",
+            ]
+        );
+    }
+
+    #[test]
+    fn draft_release_found_by_listing() {
+        let server = StubServer::start(|request, url| {
+            if request
+                .path()
+                .starts_with("/repos/owner/repo/releases/tags/")
+            {
+                Response::json(404, r#"{"message": "Not Found"}"#)
+            } else {
+                Response::json(200, format!("[{}]", release(2, "v2", true, url)))
+            }
+        });
+
+        let release = backend(&server).get_release("v2").unwrap();
+        assert_eq!(release.tag, "v2");
+        assert!(release.draft);
+        assert!(matches!(
+            backend(&server).get_release("v3"),
+            Err(ForgeError::ReleaseNotFound(tag)) if tag == "v3"
+        ));
+    }
+
+    #[test]
+    fn upload_strips_url_template() {
+        let server = StubServer::start(|request, url| match request.method.as_str() {
+            "GET" => Response::json(200, release(7, "v1", false, url)),
+            "DELETE" => Response::json(204, ""),
+            _ => Response::json(201, r#"{"id": 4, "name": "app.tar.gz"}"#),
+        });
+        let dir = TempDir::new();
+        let file = dir.write("app.tar.gz", "archive contents");
+
+        backend(&server).upload_assets("v1", &[file]).unwrap();
+
+        let requests = server.requests();
+        let calls: Vec<_> = requests
+            .iter()
+            .map(|x| format!("{} {}", x.method, x.target))
+            .collect();
+        assert_eq!(
+            calls,
+            [
+                "GET /repos/owner/repo/releases/tags/v1",
+                "DELETE /repos/owner/repo/releases/assets/3",
+                "POST /uploads/repos/owner/repo/releases/7/assets?name=app.tar.gz",
+            ]
+        );
+        assert_eq!(
+            requests[2].header("content-type"),
+            Some("application/octet-stream")
+        );
+        assert_eq!(requests[2].header("content-length"), Some("16"));
+        assert_eq!(requests[2].body, b"archive contents");
+    }
+}
@@ -0,0 +1,306 @@
+use std::path::PathBuf;
+
+use super::http::{encode, file_name, Api};
+use super::{ForgeError, ForgeRelease, NewRelease, ReleaseBackend, RemoteRepository};
+
+pub const GITLAB_TOKEN: &str = "GITLAB_TOKEN";
+pub const CI_JOB_TOKEN: &str = "CI_JOB_TOKEN";
+
+#[derive(serde::Deserialize)]
+struct GitLabLink {
+    id: u64,
+    name: String,
+}
+
+#[derive(serde::Deserialize, Default)]
+struct GitLabAssets {
+    #[serde(default)]
+    links: Vec<GitLabLink>,
+}
+
+#[derive(serde::Deserialize)]
+struct GitLabLinks {
+    #[serde(rename = "self")]
+    self_url: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct GitLabRelease {
+    tag_name: String,
+    name: Option<String>,
+
+    #[serde(default)]
+    assets: GitLabAssets,
+
+    #[serde(rename = "_links")]
+    links: Option<GitLabLinks>,
+}
+
+impl From<GitLabRelease> for ForgeRelease {
+    fn from(value: GitLabRelease) -> Self {
+        ForgeRelease {
+            tag: value.tag_name,
+            name: value.name.filter(|x| !x.is_empty()),
+            url: value.links.and_then(|x| x.self_url),
+            draft: false,
+            prerelease: false,
+            assets: value.assets.links.into_iter().map(|x| x.name).collect(),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct GitLabNewRelease<'a> {
+    tag_name: &'a str,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    target: Option<&'a str>,
+}
+
+#[derive(serde::Deserialize)]
+struct GitLabUpload {
+    url: String,
+    full_path: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct GitLabNewLink<'a> {
+    name: &'a str,
+    url: &'a str,
+    link_type: &'a str,
+}
+
+/// GitLab REST (v4) backend. Assets are stored as project uploads linked to the release
+pub struct GitLabBackend {
+    remote: RemoteRepository,
+    auth: (&'static str, String),
+    api: Api,
+}
+
+impl GitLabBackend {
+    pub fn new(remote: &RemoteRepository, token: &str) -> Self {
+        Self::with_auth(remote, ("PRIVATE-TOKEN", token.to_string()))
+    }
+
+    fn with_auth(remote: &RemoteRepository, auth: (&'static str, String)) -> Self {
+        Self {
+            remote: remote.clone(),
+            api: Api::new(&format!("{}/api/v4", remote.base_url), Some(auth.clone())),
+            auth,
+        }
+    }
+
+    /// Token from `GITLAB_TOKEN`, or the job token inside of GitLab CI
+    pub fn from_env(remote: &RemoteRepository) -> Result<Self, ForgeError> {
+        if let Ok(token) = std::env::var(GITLAB_TOKEN) {
+            Ok(Self::new(remote, &token))
+        } else if let Ok(token) = std::env::var(CI_JOB_TOKEN) {
+            Ok(Self::with_auth(remote, ("JOB-TOKEN", token)))
+        } else {
+            Err(ForgeError::MissingToken(GITLAB_TOKEN))
+        }
+    }
+
+    /// Use a different api root than `<remote>/api/v4`
+    pub fn with_api_url(mut self, url: &str) -> Self {
+        self.api = Api::new(url, Some(self.auth.clone()));
+        self
+    }
+
+    fn project(&self) -> String {
+        format!("/projects/{}", encode(&self.remote.path))
+    }
+
+    fn release(&self, tag: &str) -> Result<GitLabRelease, ForgeError> {
+        self.api
+            .get_json(&format!("{}/releases/{}", self.project(), encode(tag)), &[])
+            .map_err(|error| match error {
+                ForgeError::HttpStatus { status: 404, .. } => {
+                    ForgeError::ReleaseNotFound(tag.to_string())
+                }
+                error => error,
+            })
+    }
+}
+
+impl ReleaseBackend for GitLabBackend {
+    fn list_releases(&self) -> Result<Vec<ForgeRelease>, ForgeError> {
+        let path = format!("{}/releases", self.project());
+        let mut result = Vec::new();
+        let mut page = Some("1".to_string());
+
+        while let Some(current) = page {
+            let mut response = self
+                .api
+                .get(&path, &[("per_page", "100".to_string()), ("page", current)])?;
+            page = response
+                .headers()
+                .get("x-next-page")
+                .and_then(|x| x.to_str().ok())
+                .filter(|x| !x.is_empty())
+                .map(|x| x.to_string());
+
+            let body = response
+                .body_mut()
+                .read_to_string()
+                .map_err(|error| ForgeError::HttpError(Box::new(error)))?;
+            let releases: Vec<GitLabRelease> = serde_json::from_str(&body)?;
+            if releases.is_empty() {
+                break;
+            }
+            result.extend(releases.into_iter().map(ForgeRelease::from));
+        }
+
+        Ok(result)
+    }
+
+    fn get_release(&self, tag: &str) -> Result<ForgeRelease, ForgeError> {
+        Ok(self.release(tag)?.into())
+    }
+
+    fn create_release(&self, release: &NewRelease) -> Result<ForgeRelease, ForgeError> {
+        if release.draft {
+            return Err(ForgeError::Unsupported("Draft release"));
+        }
+        if release.prerelease {
+            return Err(ForgeError::Unsupported("Prerelease"));
+        }
+
+        let created: GitLabRelease = self.api.post_json(
+            &format!("{}/releases", self.project()),
+            &GitLabNewRelease {
+                tag_name: &release.tag,
+                name: release.title.as_deref(),
+                description: release.notes.as_deref(),
+                target: release.target.as_deref(),
+            },
+        )?;
+        Ok(created.into())
+    }
+
+    fn upload_assets(&self, tag: &str, files: &[PathBuf]) -> Result<(), ForgeError> {
+        let links_path = format!("{}/releases/{}/assets/links", self.project(), encode(tag));
+        let existing = self.release(tag)?.assets.links;
+
+        for file in files {
+            let name = file_name(file)?;
+
+            // replace assets with the same name, like `gh release upload --clobber`
+            for link in existing.iter().filter(|x| x.name == name) {
+                self.api.delete(&format!("{links_path}/{}", link.id))?;
+            }
+
+            let upload: GitLabUpload =
+                self.api
+                    .post_file(&format!("{}/uploads", self.project()), "file", file)?;
+            let url = match upload.full_path {
+                Some(full_path) => format!("{}{}", self.remote.base_url, full_path),
+                None => format!(
+                    "{}/{}{}",
+                    self.remote.base_url, self.remote.path, upload.url
+                ),
+            };
+
+            let _: GitLabLink = self.api.post_json(
+                &links_path,
+                &GitLabNewLink {
+                    name: &name,
+                    url: &url,
+                    link_type: "package",
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    fn delete_release(&self, tag: &str) -> Result<(), ForgeError> {
+        self.api
+            .delete(&format!("{}/releases/{}", self.project(), encode(tag)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::stub::{Response, StubServer};
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    fn backend(server: &StubServer) -> GitLabBackend {
+        let remote =
+            RemoteRepository::parse("git@gitlab.example.com:group/sub/project.git").unwrap();
+        GitLabBackend::new(&remote, "secret").with_api_url(&server.url)
+    }
+
+    #[test]
+    fn list_releases_follows_next_page() {
+        let server = StubServer::start(|request, _| {
+            assert_eq!(request.path(), "/projects/group%2Fsub%2Fproject/releases");
+            assert_eq!(request.header("private-token"), Some("secret"));
+            match request.query("page") {
+                Some("1") => Response::json(200, r#"[{"tag_name": "v2"}, {"tag_name": "v1"}]"#)
+                    .with_header("x-next-page", "2"),
+                _ => Response::json(200, r#"[{"tag_name": "v0"}]"#).with_header("x-next-page", ""),
+            }
+        });
+
+        let releases = backend(&server).list_releases().unwrap();
+        let tags: Vec<_> = releases.iter().map(|x| x.tag.as_str()).collect();
+        assert_eq!(tags, ["v2", "v1", "v0"]);
+        assert!(releases.iter().all(|x| !x.prerelease && !x.draft));
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn upload_replaces_existing_link() {
+        let server = StubServer::start(|request, _| {
+            match (request.method.as_str(), request.path()) {
+                ("GET", _) => Response::json(
+                    200,
+                    r#"{"tag_name": "v1", "assets": {"links": [{"id": 9, "name": "app.tar.gz"}]}}"#,
+                ),
+                ("DELETE", _) => Response::json(200, r#"{"id": 9, "name": "app.tar.gz"}"#),
+                (_, "/projects/group%2Fsub%2Fproject/uploads") => Response::json(
+                    201,
+                    r#"{"url": "/uploads/abc/app.tar.gz", "full_path": "/group/sub/project/uploads/abc/app.tar.gz"}"#,
+                ),
+                _ => Response::json(201, r#"{"id": 10, "name": "app.tar.gz"}"#),
+            }
+        });
+        let dir = TempDir::new();
+        let file = dir.write("app.tar.gz", "archive contents");
+
+        backend(&server).upload_assets("v1", &[file]).unwrap();
+
+        let requests = server.requests();
+        let calls: Vec<_> = requests
+            .iter()
+            .map(|x| format!("{} {}", x.method, x.target))
+            .collect();
+        assert_eq!(
+            calls,
+            [
+                "GET /projects/group%2Fsub%2Fproject/releases/v1",
+                "DELETE /projects/group%2Fsub%2Fproject/releases/v1/assets/links/9",
+                "POST /projects/group%2Fsub%2Fproject/uploads",
+                "POST /projects/group%2Fsub%2Fproject/releases/v1/assets/links",
+            ]
+        );
+
+        let upload = String::from_utf8_lossy(&requests[2].body);
+        assert!(upload.contains("name=\"file\"; filename=\"app.tar.gz\""));
+        assert!(upload.contains("archive contents"));
+
+        let link: serde_json::Value = serde_json::from_slice(&requests[3].body).unwrap();
+        assert_eq!(link["name"], "app.tar.gz");
+        assert_eq!(
+            link["url"],
+            "https://gitlab.example.com/group/sub/project/uploads/abc/app.tar.gz"
+        );
+    }
+}
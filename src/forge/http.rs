@@ -0,0 +1,183 @@
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use ureq::http::Response;
+use ureq::{Body, SendBody};
+
+use super::ForgeError;
+
+/// Thin JSON-over-HTTP client shared by the REST backends
+pub(crate) struct Api {
+    agent: ureq::Agent,
+    base_url: String,
+    auth: Option<(&'static str, String)>,
+}
+
+impl Api {
+    pub fn new(base_url: &str, auth: Option<(&'static str, String)>) -> Self {
+        let agent = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .build()
+            .into();
+
+        Self {
+            agent,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            auth,
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        if path.starts_with("http://") || path.starts_with("https://") {
+            path.to_string()
+        } else {
+            format!("{}{}", self.base_url, path)
+        }
+    }
+
+    fn check(
+        url: &str,
+        response: Result<Response<Body>, ureq::Error>,
+    ) -> Result<Response<Body>, ForgeError> {
+        let mut response = response.map_err(|error| ForgeError::HttpError(Box::new(error)))?;
+        let status = response.status().as_u16();
+
+        if (200..300).contains(&status) {
+            Ok(response)
+        } else {
+            Err(ForgeError::HttpStatus {
+                url: url.to_string(),
+                status,
+                body: response.body_mut().read_to_string().unwrap_or_default(),
+            })
+        }
+    }
+
+    fn with_auth<B>(&self, request: ureq::RequestBuilder<B>) -> ureq::RequestBuilder<B> {
+        let request = request.header("Accept", "application/json");
+        match &self.auth {
+            Some((header, value)) => request.header(*header, value),
+            None => request,
+        }
+    }
+
+    pub fn get(&self, path: &str, query: &[(&str, String)]) -> Result<Response<Body>, ForgeError> {
+        let url = self.url(path);
+        let request = self.with_auth(self.agent.get(&url));
+        let request = query
+            .iter()
+            .fold(request, |request, (key, value)| request.query(*key, value));
+        Self::check(&url, request.call())
+    }
+
+    pub fn get_json<T>(&self, path: &str, query: &[(&str, String)]) -> Result<T, ForgeError>
+    where
+        T: DeserializeOwned,
+    {
+        read_json(self.get(path, query)?)
+    }
+
+    pub fn post_json<T, B>(&self, path: &str, body: &B) -> Result<T, ForgeError>
+    where
+        T: DeserializeOwned,
+        B: serde::Serialize,
+    {
+        let url = self.url(path);
+        let request = self.with_auth(self.agent.post(&url));
+        read_json(Self::check(&url, request.send_json(body))?)
+    }
+
+    /// Send `length` bytes read from `body`, without buffering them
+    pub fn post_stream<T, R>(
+        &self,
+        path: &str,
+        content_type: &str,
+        length: u64,
+        body: R,
+    ) -> Result<T, ForgeError>
+    where
+        T: DeserializeOwned,
+        R: Read + 'static,
+    {
+        let url = self.url(path);
+        let request = self
+            .with_auth(self.agent.post(&url))
+            .header("Content-Type", content_type)
+            .header("Content-Length", length.to_string());
+        read_json(Self::check(
+            &url,
+            request.send(SendBody::from_owned_reader(body)),
+        )?)
+    }
+
+    /// Upload a file as a single `multipart/form-data` field
+    pub fn post_file<T>(&self, path: &str, field: &str, file: &Path) -> Result<T, ForgeError>
+    where
+        T: DeserializeOwned,
+    {
+        let (content_type, length, body) = multipart(field, file)?;
+        self.post_stream(path, &content_type, length, body)
+    }
+
+    pub fn delete(&self, path: &str) -> Result<(), ForgeError> {
+        let url = self.url(path);
+        let request = self.with_auth(self.agent.delete(&url));
+        Self::check(&url, request.call())?;
+        Ok(())
+    }
+}
+
+fn read_json<T>(mut response: Response<Body>) -> Result<T, ForgeError>
+where
+    T: DeserializeOwned,
+{
+    let body = response
+        .body_mut()
+        .read_to_string()
+        .map_err(|error| ForgeError::HttpError(Box::new(error)))?;
+    Ok(serde_json::from_str(&body)?)
+}
+
+pub(crate) fn file_name(file: &Path) -> Result<String, ForgeError> {
+    file.file_name()
+        .map(|x| x.to_string_lossy().to_string())
+        .ok_or_else(|| ForgeError::InvalidAsset(file.to_path_buf()))
+}
+
+/// Multipart body streaming the file between the field header and the trailer
+fn multipart(field: &str, file: &Path) -> Result<(String, u64, impl Read), ForgeError> {
+    let boundary = "----xtask-toolkit-boundary-7MA4YWxkTrZu0gW";
+    let filename = file_name(file)?.replace('"', "%22");
+
+    let header = format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"{field}\"; filename=\"{filename}\"\r\nContent-Type: application/octet-stream\r\n\r\n"
+    )
+    .into_bytes();
+    let trailer = format!("\r\n--{boundary}--\r\n").into_bytes();
+
+    let contents = std::fs::File::open(file)?;
+    let length = header.len() as u64 + contents.metadata()?.len() + trailer.len() as u64;
+    let body = Cursor::new(header)
+        .chain(contents)
+        .chain(Cursor::new(trailer));
+
+    Ok((
+        format!("multipart/form-data; boundary={boundary}"),
+        length,
+        body,
+    ))
+}
+
+/// Percent-encode a single path segment or query value
+pub(crate) fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
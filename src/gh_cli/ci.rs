@@ -0,0 +1,490 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use xshell::{cmd, Shell};
+
+const RUN_FIELDS: &str = "databaseId,name,displayTitle,workflowName,status,conclusion,headSha,headBranch,event,url,createdAt";
+
+#[derive(Debug, thiserror::Error)]
+pub enum CiError {
+    #[error(transparent)]
+    XShellError(#[from] xshell::Error),
+
+    #[error(transparent)]
+    SerdeError(#[from] serde_json::Error),
+
+    #[error("CI for {sha} did not complete within {timeout:?}")]
+    Timeout { sha: String, timeout: Duration },
+}
+
+/// Status of a check run or workflow run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Queued,
+    InProgress,
+    Completed,
+    Waiting,
+    Requested,
+    Pending,
+
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunConclusion {
+    Success,
+    Failure,
+    Neutral,
+    Cancelled,
+    Skipped,
+    TimedOut,
+    ActionRequired,
+    Stale,
+    StartupFailure,
+
+    #[serde(other)]
+    Unknown,
+}
+
+impl RunConclusion {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Self::Success | Self::Neutral | Self::Skipped)
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct CheckRun {
+    pub id: u64,
+    pub name: String,
+    pub status: RunStatus,
+    pub conclusion: Option<RunConclusion>,
+    pub html_url: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct CheckRunsPage {
+    check_runs: Vec<CheckRun>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusState {
+    Pending,
+    Success,
+    Failure,
+    Error,
+}
+
+/// Commit status, as reported by external CI systems
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct CommitStatus {
+    pub context: String,
+    pub state: StatusState,
+    pub target_url: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct CombinedStatus {
+    statuses: Vec<CommitStatus>,
+}
+
+/// Overall CI result of a commit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiState {
+    Pending,
+    Success,
+    Failure,
+}
+
+/// Check runs (GitHub Actions and apps) and commit statuses of a single commit
+#[derive(Debug, Clone)]
+pub struct CommitChecks {
+    pub sha: String,
+    pub check_runs: Vec<CheckRun>,
+    pub statuses: Vec<CommitStatus>,
+}
+
+impl CommitChecks {
+    pub fn fetch(sha: &str) -> Result<Self, CiError> {
+        let sh = Shell::new()?;
+
+        let endpoint = format!("repos/{{owner}}/{{repo}}/commits/{sha}/check-runs?per_page=100");
+        let output = cmd!(sh, "gh api --paginate {endpoint}").read()?;
+        // --paginate prints every page as a separate json document
+        let mut check_runs = Vec::new();
+        for page in serde_json::Deserializer::from_str(&output).into_iter::<CheckRunsPage>() {
+            check_runs.extend(page?.check_runs);
+        }
+
+        let endpoint = format!("repos/{{owner}}/{{repo}}/commits/{sha}/status");
+        let combined: CombinedStatus =
+            serde_json::from_str(&cmd!(sh, "gh api {endpoint}").read()?)?;
+
+        Ok(Self {
+            sha: sha.to_string(),
+            check_runs,
+            statuses: combined.statuses,
+        })
+    }
+
+    /// Poll every `interval` until nothing is pending, failing after `timeout`
+    pub fn wait(sha: &str, timeout: Duration, interval: Duration) -> Result<Self, CiError> {
+        let start = Instant::now();
+
+        loop {
+            let checks = Self::fetch(sha)?;
+            if checks.state() != CiState::Pending {
+                return Ok(checks);
+            }
+            if start.elapsed() + interval > timeout {
+                return Err(CiError::Timeout {
+                    sha: sha.to_string(),
+                    timeout,
+                });
+            }
+            std::thread::sleep(interval);
+        }
+    }
+
+    /// Failure wins over pending, pending over success. A commit without any checks is pending
+    pub fn state(&self) -> CiState {
+        let failed = self
+            .check_runs
+            .iter()
+            .any(|x| x.conclusion.is_some_and(|x| !x.is_ok()))
+            || self
+                .statuses
+                .iter()
+                .any(|x| matches!(x.state, StatusState::Failure | StatusState::Error));
+
+        let pending = self
+            .check_runs
+            .iter()
+            .any(|x| x.status != RunStatus::Completed)
+            || self
+                .statuses
+                .iter()
+                .any(|x| x.state == StatusState::Pending);
+
+        if failed {
+            CiState::Failure
+        } else if pending || (self.check_runs.is_empty() && self.statuses.is_empty()) {
+            CiState::Pending
+        } else {
+            CiState::Success
+        }
+    }
+
+    /// Names of failed check runs and contexts of failed statuses
+    pub fn failures(&self) -> Vec<&str> {
+        let check_runs = self
+            .check_runs
+            .iter()
+            .filter(|x| x.conclusion.is_some_and(|x| !x.is_ok()))
+            .map(|x| x.name.as_str());
+        let statuses = self
+            .statuses
+            .iter()
+            .filter(|x| matches!(x.state, StatusState::Failure | StatusState::Error))
+            .map(|x| x.context.as_str());
+        check_runs.chain(statuses).collect()
+    }
+}
+
+/// Workflow run as returned by `gh run list`
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkflowRun {
+    pub database_id: u64,
+    pub name: String,
+    pub display_title: String,
+    pub workflow_name: String,
+    pub status: RunStatus,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub conclusion: Option<RunConclusion>,
+    pub head_sha: String,
+    pub head_branch: String,
+    pub event: String,
+    pub url: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// `gh run list` reports runs without a conclusion yet as `""` instead of `null`
+fn empty_as_none<'de, D>(deserializer: D) -> Result<Option<RunConclusion>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::IntoDeserializer;
+    use serde::Deserialize;
+
+    match Option::<String>::deserialize(deserializer)?.as_deref() {
+        None | Some("") => Ok(None),
+        Some(conclusion) => RunConclusion::deserialize(conclusion.into_deserializer()).map(Some),
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WorkflowRunQuery {
+    workflow: Option<String>,
+    branch: Option<String>,
+    commit: Option<String>,
+    event: Option<String>,
+    limit: Option<usize>,
+}
+
+impl WorkflowRunQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Workflow name or file name (e.g. `ci.yml`)
+    pub fn with_workflow<S>(&mut self, workflow: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.workflow = Some(workflow.into());
+        self
+    }
+
+    pub fn with_branch<S>(&mut self, branch: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.branch = Some(branch.into());
+        self
+    }
+
+    pub fn with_commit<S>(&mut self, sha: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.commit = Some(sha.into());
+        self
+    }
+
+    /// Trigger, e.g. `push` or `workflow_dispatch`
+    pub fn with_event<S>(&mut self, event: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.event = Some(event.into());
+        self
+    }
+
+    /// Defaults to the `gh` default (20)
+    pub fn limit(&mut self, limit: usize) -> &mut Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn fetch(&self) -> Result<Vec<WorkflowRun>, CiError> {
+        let sh = Shell::new()?;
+
+        let mut args = Vec::new();
+        let options = [
+            ("--workflow", &self.workflow),
+            ("--branch", &self.branch),
+            ("--commit", &self.commit),
+            ("--event", &self.event),
+        ];
+        for (flag, value) in options {
+            if let Some(value) = value {
+                args.extend([flag.to_string(), value.clone()]);
+            }
+        }
+        if let Some(limit) = self.limit {
+            args.extend(["--limit".to_string(), limit.to_string()]);
+        }
+
+        Ok(serde_json::from_str(
+            &cmd!(sh, "gh run list --json {RUN_FIELDS} {args...}").read()?,
+        )?)
+    }
+}
+
+/// Trigger of a workflow with the `workflow_dispatch` event
+#[derive(Debug, Clone)]
+pub struct WorkflowDispatch {
+    workflow: String,
+    git_ref: Option<String>,
+    inputs: HashMap<String, String>,
+}
+
+impl WorkflowDispatch {
+    /// Workflow name, id or file name (e.g. `release.yml`)
+    pub fn new<S>(workflow: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            workflow: workflow.into(),
+            git_ref: None,
+            inputs: HashMap::new(),
+        }
+    }
+
+    /// Branch or tag with the workflow version to run. Defaults to the default branch
+    pub fn with_ref<S>(&mut self, git_ref: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.git_ref = Some(git_ref.into());
+        self
+    }
+
+    pub fn with_input<K, V>(&mut self, key: K, value: V) -> &mut Self
+    where
+        K: Into<String>,
+        V: ToString,
+    {
+        self.inputs.insert(key.into(), value.to_string());
+        self
+    }
+
+    pub fn run(&self) -> Result<(), CiError> {
+        let sh = Shell::new()?;
+        let workflow = &self.workflow;
+        let git_ref = self
+            .git_ref
+            .iter()
+            .flat_map(|x| ["--ref".to_string(), x.clone()])
+            .collect::<Vec<_>>();
+        let mut inputs = self.inputs.iter().collect::<Vec<_>>();
+        inputs.sort();
+        let inputs = inputs
+            .into_iter()
+            .flat_map(|(key, value)| ["-f".to_string(), format!("{key}={value}")])
+            .collect::<Vec<_>>();
+
+        cmd!(sh, "gh workflow run {workflow} {git_ref...} {inputs...}").read()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_run(name: &str, status: RunStatus, conclusion: Option<RunConclusion>) -> CheckRun {
+        CheckRun {
+            id: 1,
+            name: name.to_string(),
+            status,
+            conclusion,
+            html_url: None,
+        }
+    }
+
+    fn completed(name: &str, conclusion: RunConclusion) -> CheckRun {
+        check_run(name, RunStatus::Completed, Some(conclusion))
+    }
+
+    fn status(context: &str, state: StatusState) -> CommitStatus {
+        CommitStatus {
+            context: context.to_string(),
+            state,
+            target_url: None,
+        }
+    }
+
+    fn checks(check_runs: Vec<CheckRun>, statuses: Vec<CommitStatus>) -> CommitChecks {
+        CommitChecks {
+            sha: "0123abc".to_string(),
+            check_runs,
+            statuses,
+        }
+    }
+
+    #[test]
+    fn commit_without_checks_is_pending() {
+        assert_eq!(checks(vec![], vec![]).state(), CiState::Pending);
+    }
+
+    #[test]
+    fn completed_checks_succeed() {
+        let succeeded = checks(
+            vec![
+                completed("test", RunConclusion::Success),
+                completed("lint", RunConclusion::Neutral),
+                completed("deploy", RunConclusion::Skipped),
+            ],
+            vec![status("ci/external", StatusState::Success)],
+        );
+        assert_eq!(succeeded.state(), CiState::Success);
+        assert!(succeeded.failures().is_empty());
+    }
+
+    #[test]
+    fn running_checks_are_pending() {
+        let running = checks(
+            vec![check_run("test", RunStatus::InProgress, None)],
+            vec![status("ci/external", StatusState::Success)],
+        );
+        assert_eq!(running.state(), CiState::Pending);
+        let waiting = checks(
+            vec![completed("test", RunConclusion::Success)],
+            vec![status("ci/external", StatusState::Pending)],
+        );
+        assert_eq!(waiting.state(), CiState::Pending);
+    }
+
+    #[test]
+    fn failures_win_over_pending() {
+        let mixed = checks(
+            vec![
+                completed("test", RunConclusion::Failure),
+                completed("lint", RunConclusion::Success),
+                check_run("deploy", RunStatus::Queued, None),
+            ],
+            vec![
+                status("ci/external", StatusState::Error),
+                status("ci/other", StatusState::Pending),
+            ],
+        );
+        assert_eq!(mixed.state(), CiState::Failure);
+        assert_eq!(mixed.failures(), ["test", "ci/external"]);
+
+        for conclusion in [RunConclusion::Cancelled, RunConclusion::TimedOut] {
+            let failed = checks(vec![completed("test", conclusion)], vec![]);
+            assert_eq!(failed.state(), CiState::Failure);
+        }
+        let failed = checks(vec![], vec![status("ci", StatusState::Failure)]);
+        assert_eq!(failed.state(), CiState::Failure);
+    }
+
+    #[test]
+    fn parses_workflow_runs() {
+        let json = r#"[
+            {"databaseId": 2, "name": "CI", "displayTitle": "Fix tests", "workflowName": "CI",
+             "status": "in_progress", "conclusion": "", "headSha": "0123abc",
+             "headBranch": "main", "event": "push", "url": "https://github.com/o/r/actions/runs/2",
+             "createdAt": "2024-05-01T10:00:00Z"},
+            {"databaseId": 1, "name": "CI", "displayTitle": "Fix tests", "workflowName": "CI",
+             "status": "completed", "conclusion": "success", "headSha": "0123abc",
+             "headBranch": "main", "event": "push", "url": "https://github.com/o/r/actions/runs/1",
+             "createdAt": "2024-05-01T09:00:00Z"},
+            {"databaseId": 0, "name": "CI", "displayTitle": "Fix tests", "workflowName": "CI",
+             "status": "completed", "conclusion": "something_new", "headSha": "0123abc",
+             "headBranch": "main", "event": "push", "url": "https://github.com/o/r/actions/runs/0",
+             "createdAt": "2024-05-01T08:00:00Z"}
+        ]"#;
+
+        let runs: Vec<WorkflowRun> = serde_json::from_str(json).unwrap();
+        let states = runs
+            .iter()
+            .map(|x| (x.status, x.conclusion))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            states,
+            [
+                (RunStatus::InProgress, None),
+                (RunStatus::Completed, Some(RunConclusion::Success)),
+                (RunStatus::Completed, Some(RunConclusion::Unknown)),
+            ]
+        );
+    }
+}
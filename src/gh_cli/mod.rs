@@ -0,0 +1,407 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use xshell::{cmd, Shell};
+
+mod ci;
+mod pull_request;
+mod query;
+mod tag_scheme;
+
+pub use ci::{
+    CheckRun, CiError, CiState, CommitChecks, CommitStatus, RunConclusion, RunStatus, StatusState,
+    WorkflowDispatch, WorkflowRun, WorkflowRunQuery,
+};
+pub use pull_request::{
+    Label, MergeStateStatus, Mergeable, PullRequest, PullRequestError, PullRequestInfo,
+    PullRequestState,
+};
+pub use query::ReleaseQuery;
+pub use tag_scheme::{TagParseError, TagScheme};
+
+#[derive(serde::Deserialize)]
+struct GhResponseAsset {
+    pub name: String,
+}
+
+/// Release as returned by the REST api
+#[derive(serde::Deserialize)]
+struct GhResponse {
+    pub tag_name: String,
+
+    #[serde(default)]
+    pub draft: bool,
+
+    #[serde(default)]
+    pub prerelease: bool,
+
+    pub published_at: Option<DateTime<Utc>>,
+    pub html_url: Option<String>,
+
+    #[serde(default)]
+    pub assets: Vec<GhResponseAsset>,
+}
+
+pub struct Release {
+    pub name: String,
+    pub version: semver::Version,
+    pub draft: bool,
+    pub prelease: bool,
+
+    /// Only known for releases fetched from GitHub
+    pub published_at: Option<DateTime<Utc>>,
+    pub url: Option<String>,
+    pub assets: Vec<String>,
+
+    tag_scheme: TagScheme,
+    title: Option<String>,
+    notes: ReleaseNotes,
+    target: Option<String>,
+    latest: Option<bool>,
+    verify_tag: bool,
+    discussion_category: Option<String>,
+}
+
+/// Source of the release notes
+#[derive(Debug, Clone, Default)]
+pub enum ReleaseNotes {
+    /// Let GitHub generate the notes from merged pull requests
+    #[default]
+    Generate,
+
+    Text(String),
+    File(PathBuf),
+    Empty,
+}
+
+/// Release as returned by `gh release view`
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GhRelease {
+    pub url: String,
+    pub id: String,
+    pub database_id: u64,
+    pub tag_name: String,
+
+    #[serde(default)]
+    pub is_draft: bool,
+
+    #[serde(default)]
+    pub is_prerelease: bool,
+
+    #[serde(default)]
+    pub assets: Vec<GhAsset>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct GhAsset {
+    pub name: String,
+    pub size: u64,
+    pub url: String,
+}
+
+impl GhRelease {
+    pub fn view(tag: &str) -> Result<Self, ReleaseError> {
+        let sh = Shell::new()?;
+        Ok(serde_json::from_str(
+            &cmd!(
+                sh,
+                "gh release view {tag} --json url,id,databaseId,tagName,isDraft,isPrerelease,assets"
+            )
+            .read()?,
+        )?)
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct GeneratedNotes {
+    body: String,
+}
+
+impl TryFrom<GhResponse> for Release {
+    type Error = TagParseError;
+    fn try_from(value: GhResponse) -> Result<Self, Self::Error> {
+        Self::from_gh_response(value, &TagScheme::default())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum GetFromGHError {
+    #[error(transparent)]
+    XShellError(#[from] xshell::Error),
+
+    #[error(transparent)]
+    SerdeError(#[from] serde_json::Error),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReleaseError {
+    #[error(transparent)]
+    XShellError(#[from] xshell::Error),
+
+    #[error(transparent)]
+    SerdeError(#[from] serde_json::Error),
+}
+
+#[derive(Default)]
+pub enum ReleaseMode {
+    #[default]
+    Normal,
+
+    Draft,
+    Prerelease,
+}
+
+impl Release {
+    pub fn new(name: &str, version: &str) -> Result<Self, semver::Error> {
+        let version = semver::Version::parse(version)?;
+        Ok(Self::from_parts(name, version))
+    }
+
+    fn from_parts(name: &str, version: semver::Version) -> Self {
+        Release {
+            name: name.to_string(),
+            version,
+            draft: false,
+            prelease: false,
+            published_at: None,
+            url: None,
+            assets: Vec::new(),
+            tag_scheme: TagScheme::default(),
+            title: None,
+            notes: ReleaseNotes::default(),
+            target: None,
+            latest: None,
+            verify_tag: false,
+            discussion_category: None,
+        }
+    }
+
+    fn from_gh_response(value: GhResponse, scheme: &TagScheme) -> Result<Self, TagParseError> {
+        let (name, version) = scheme.parse(&value.tag_name)?;
+
+        Ok(Release {
+            draft: value.draft,
+            prelease: value.prerelease,
+            published_at: value.published_at,
+            url: value.html_url,
+            assets: value.assets.into_iter().map(|x| x.name).collect(),
+            tag_scheme: scheme.clone(),
+            ..Release::from_parts(&name, version)
+        })
+    }
+
+    pub fn tag(&self) -> String {
+        self.tag_scheme.format(&self.name, &self.version)
+    }
+
+    /// How the tag is built from name and version. Defaults to [`TagScheme::NameVersion`]
+    pub fn with_tag_scheme(&mut self, scheme: TagScheme) -> &mut Self {
+        self.tag_scheme = scheme;
+        self
+    }
+
+    pub fn with_release_mode(&mut self, mode: ReleaseMode) -> &mut Self {
+        self.draft = matches!(mode, ReleaseMode::Draft);
+        self.prelease = matches!(mode, ReleaseMode::Prerelease);
+        self
+    }
+
+    /// Release title. Defaults to the tag name
+    pub fn with_title<S>(&mut self, title: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Release notes. Generated by GitHub by default
+    pub fn with_notes(&mut self, notes: ReleaseNotes) -> &mut Self {
+        self.notes = notes;
+        self
+    }
+
+    /// Branch or commit SHA the tag is created from, if it does not exist yet
+    pub fn with_target<S>(&mut self, target: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.target = Some(target.into());
+        self
+    }
+
+    /// Explicitly mark (or not) the release as "Latest". Decided by GitHub by default
+    pub fn with_latest(&mut self, latest: bool) -> &mut Self {
+        self.latest = Some(latest);
+        self
+    }
+
+    /// Abort the release if the tag does not exist yet, instead of creating it
+    pub fn verify_tag(&mut self) -> &mut Self {
+        self.verify_tag = true;
+        self
+    }
+
+    /// Start a discussion in the given category
+    pub fn with_discussion_category<S>(&mut self, category: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.discussion_category = Some(category.into());
+        self
+    }
+
+    fn create_args(&self) -> Vec<String> {
+        let mut args = vec!["release".to_string(), "create".to_string(), self.tag()];
+
+        match &self.notes {
+            ReleaseNotes::Generate => args.push("--generate-notes".to_string()),
+            ReleaseNotes::Text(text) => args.extend(["--notes".to_string(), text.clone()]),
+            ReleaseNotes::File(path) => {
+                args.extend(["--notes-file".to_string(), path.display().to_string()])
+            }
+            ReleaseNotes::Empty => args.extend(["--notes".to_string(), String::new()]),
+        }
+
+        if let Some(title) = &self.title {
+            args.extend(["--title".to_string(), title.clone()]);
+        }
+        if let Some(target) = &self.target {
+            args.extend(["--target".to_string(), target.clone()]);
+        }
+        if let Some(latest) = self.latest {
+            args.push(format!("--latest={latest}"));
+        }
+        if let Some(category) = &self.discussion_category {
+            args.extend(["--discussion-category".to_string(), category.clone()]);
+        }
+        if self.verify_tag {
+            args.push("--verify-tag".to_string());
+        }
+        if self.draft {
+            args.push("--draft".to_string());
+        }
+        if self.prelease {
+            args.push("--prerelease".to_string());
+        }
+
+        args
+    }
+
+    /// All releases of the current repository. Tags not matching [`TagScheme::NameVersion`] are reported as errors
+    pub fn get_from_gh() -> Result<Vec<Result<Self, TagParseError>>, GetFromGHError> {
+        ReleaseQuery::new().fetch()
+    }
+
+    pub fn get_from_gh_with_scheme(
+        scheme: &TagScheme,
+    ) -> Result<Vec<Result<Self, TagParseError>>, GetFromGHError> {
+        ReleaseQuery::new().with_tag_scheme(scheme.clone()).fetch()
+    }
+
+    /// Query with filtering, sorting and limits. See [`ReleaseQuery`]
+    pub fn query() -> ReleaseQuery {
+        ReleaseQuery::new()
+    }
+
+    pub fn release<T, I>(&self, files: T) -> Result<GhRelease, ReleaseError>
+    where
+        T: IntoIterator<Item = I>,
+        I: AsRef<Path> + Sized,
+    {
+        let sh = Shell::new()?;
+        let files = files
+            .into_iter()
+            .map(|x| x.as_ref().display().to_string())
+            .collect::<Vec<_>>();
+
+        sh.cmd("gh").args(self.create_args()).args(files).read()?;
+
+        GhRelease::view(&self.tag())
+    }
+
+    /// Upload assets to an already existing release. `clobber` overwrites assets with the same name
+    pub fn upload<T, I>(&self, files: T, clobber: bool) -> Result<(), ReleaseError>
+    where
+        T: IntoIterator<Item = I>,
+        I: AsRef<Path> + Sized,
+    {
+        let sh = Shell::new()?;
+        let tag = self.tag();
+        let files = files
+            .into_iter()
+            .map(|x| x.as_ref().display().to_string())
+            .collect::<Vec<_>>();
+        let clobber = clobber.then_some("--clobber");
+
+        cmd!(sh, "gh release upload {tag} {files...} {clobber...}").read()?;
+        Ok(())
+    }
+
+    /// Turn a draft into a published release
+    pub fn publish(&mut self) -> Result<GhRelease, ReleaseError> {
+        let sh = Shell::new()?;
+        let tag = self.tag();
+
+        cmd!(sh, "gh release edit {tag} --draft=false").read()?;
+        self.draft = false;
+        GhRelease::view(&tag)
+    }
+
+    /// Replace notes of an existing release. [`ReleaseNotes::Generate`] asks GitHub for fresh notes
+    pub fn edit_notes(&self, notes: &ReleaseNotes) -> Result<(), ReleaseError> {
+        let sh = Shell::new()?;
+        let tag = self.tag();
+
+        match notes {
+            ReleaseNotes::Generate => {
+                let endpoint = "repos/{owner}/{repo}/releases/generate-notes";
+                let field = format!("tag_name={tag}");
+                let generated: GeneratedNotes =
+                    serde_json::from_str(&cmd!(sh, "gh api {endpoint} -f {field}").read()?)?;
+                let body = generated.body;
+                cmd!(sh, "gh release edit {tag} --notes {body}").read()?
+            }
+            ReleaseNotes::Text(body) => cmd!(sh, "gh release edit {tag} --notes {body}").read()?,
+            ReleaseNotes::File(path) => {
+                cmd!(sh, "gh release edit {tag} --notes-file {path}").read()?
+            }
+            ReleaseNotes::Empty => cmd!(sh, "gh release edit {tag} --notes=").read()?,
+        };
+        Ok(())
+    }
+
+    /// Delete the release. With `cleanup_tag` the git tag is removed from the remote as well
+    pub fn delete(&self, cleanup_tag: bool) -> Result<(), ReleaseError> {
+        let sh = Shell::new()?;
+        let tag = self.tag();
+        let cleanup_tag = cleanup_tag.then_some("--cleanup-tag");
+
+        cmd!(sh, "gh release delete {tag} --yes {cleanup_tag...}").read()?;
+        Ok(())
+    }
+
+    /// Download assets matching any of the glob `patterns` (all assets if empty) into `dir`
+    ///
+    /// Existing files are overwritten
+    pub fn download<P>(&self, dir: P, patterns: &[&str]) -> Result<(), ReleaseError>
+    where
+        P: AsRef<Path>,
+    {
+        let sh = Shell::new()?;
+        let tag = self.tag();
+        let dir = dir.as_ref();
+        let patterns = patterns
+            .iter()
+            .flat_map(|pattern| ["--pattern", pattern])
+            .collect::<Vec<_>>();
+
+        cmd!(
+            sh,
+            "gh release download {tag} --dir {dir} --clobber {patterns...}"
+        )
+        .read()?;
+        Ok(())
+    }
+}
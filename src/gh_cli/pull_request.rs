@@ -0,0 +1,255 @@
+use chrono::{DateTime, Utc};
+use xshell::{cmd, Shell};
+
+const PR_FIELDS: &str =
+    "number,url,title,state,headRefName,baseRefName,isDraft,mergeable,mergeStateStatus,mergedAt,labels";
+
+#[derive(Debug, thiserror::Error)]
+pub enum PullRequestError {
+    #[error(transparent)]
+    XShellError(#[from] xshell::Error),
+
+    #[error(transparent)]
+    SerdeError(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PullRequestState {
+    Open,
+    Closed,
+    Merged,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Mergeable {
+    Mergeable,
+    Conflicting,
+    Unknown,
+}
+
+/// Detailed merge readiness, as reported by GitHub
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MergeStateStatus {
+    Behind,
+    Blocked,
+    Clean,
+    Dirty,
+    Draft,
+    HasHooks,
+    Unstable,
+
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Label {
+    pub name: String,
+}
+
+/// Pull request as returned by `gh pr view`
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PullRequestInfo {
+    pub number: u64,
+    pub url: String,
+    pub title: String,
+    pub state: PullRequestState,
+    pub head_ref_name: String,
+    pub base_ref_name: String,
+    pub is_draft: bool,
+    pub mergeable: Mergeable,
+    pub merge_state_status: MergeStateStatus,
+    pub merged_at: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    pub labels: Vec<Label>,
+}
+
+impl PullRequestInfo {
+    /// Pull request by number, url or head branch name
+    pub fn view(pr: &str) -> Result<Self, PullRequestError> {
+        let sh = Shell::new()?;
+        Ok(serde_json::from_str(
+            &cmd!(sh, "gh pr view {pr} --json {PR_FIELDS}").read()?,
+        )?)
+    }
+
+    /// First open pull request carrying the label
+    pub fn find_open_by_label(label: &str) -> Result<Option<Self>, PullRequestError> {
+        let sh = Shell::new()?;
+        let found: Vec<Self> = serde_json::from_str(
+            &cmd!(
+                sh,
+                "gh pr list --state open --label {label} --limit 1 --json {PR_FIELDS}"
+            )
+            .read()?,
+        )?;
+        Ok(found.into_iter().next())
+    }
+
+    /// Open pull request from the given branch
+    pub fn find_open_by_head(head: &str) -> Result<Option<Self>, PullRequestError> {
+        let sh = Shell::new()?;
+        let found: Vec<Self> = serde_json::from_str(
+            &cmd!(
+                sh,
+                "gh pr list --state open --head {head} --limit 1 --json {PR_FIELDS}"
+            )
+            .read()?,
+        )?;
+        Ok(found.into_iter().next())
+    }
+
+    /// Refresh state, mergeability and merge status
+    pub fn refresh(&self) -> Result<Self, PullRequestError> {
+        Self::view(&self.number.to_string())
+    }
+
+    pub fn is_merged(&self) -> bool {
+        self.state == PullRequestState::Merged
+    }
+
+    /// Open, not a draft, without conflicts and with all the required checks passing
+    pub fn is_ready_to_merge(&self) -> bool {
+        self.state == PullRequestState::Open
+            && !self.is_draft
+            && self.mergeable == Mergeable::Mergeable
+            && matches!(
+                self.merge_state_status,
+                MergeStateStatus::Clean | MergeStateStatus::HasHooks
+            )
+    }
+}
+
+/// Pull request to open (or update) from an already pushed branch
+#[derive(Debug, Clone)]
+pub struct PullRequest {
+    head: String,
+    base: Option<String>,
+    title: String,
+    body: String,
+    labels: Vec<String>,
+    reviewers: Vec<String>,
+    draft: bool,
+}
+
+impl PullRequest {
+    pub fn new<H, T>(head: H, title: T) -> Self
+    where
+        H: Into<String>,
+        T: Into<String>,
+    {
+        Self {
+            head: head.into(),
+            base: None,
+            title: title.into(),
+            body: String::new(),
+            labels: Vec::new(),
+            reviewers: Vec::new(),
+            draft: false,
+        }
+    }
+
+    /// Target branch. Defaults to the default branch of the repository
+    pub fn with_base<S>(&mut self, base: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.base = Some(base.into());
+        self
+    }
+
+    pub fn with_body<S>(&mut self, body: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.body = body.into();
+        self
+    }
+
+    pub fn with_label<S>(&mut self, label: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.labels.push(label.into());
+        self
+    }
+
+    /// User login or `org/team`
+    pub fn with_reviewer<S>(&mut self, reviewer: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.reviewers.push(reviewer.into());
+        self
+    }
+
+    pub fn draft(&mut self) -> &mut Self {
+        self.draft = true;
+        self
+    }
+
+    fn repeated<'a>(flag: &'a str, values: &'a [String]) -> Vec<&'a str> {
+        values
+            .iter()
+            .flat_map(|value| [flag, value.as_str()])
+            .collect()
+    }
+
+    pub fn create(&self) -> Result<PullRequestInfo, PullRequestError> {
+        let sh = Shell::new()?;
+        let head = &self.head;
+        let title = &self.title;
+        let body = &self.body;
+        let base = Self::repeated("--base", self.base.as_slice());
+        let labels = Self::repeated("--label", &self.labels);
+        let reviewers = Self::repeated("--reviewer", &self.reviewers);
+        let draft = self.draft.then_some("--draft");
+
+        cmd!(
+            sh,
+            "gh pr create --head {head} --title {title} --body {body} {base...} {labels...} {reviewers...} {draft...}"
+        )
+        .read()?;
+        PullRequestInfo::view(head)
+    }
+
+    /// Overwrite title, body and base of an existing pull request. Labels and reviewers are added
+    pub fn update(&self, number: u64) -> Result<PullRequestInfo, PullRequestError> {
+        let sh = Shell::new()?;
+        let number = number.to_string();
+        let title = &self.title;
+        let body = &self.body;
+        let base = Self::repeated("--base", self.base.as_slice());
+        let labels = Self::repeated("--add-label", &self.labels);
+        let reviewers = Self::repeated("--add-reviewer", &self.reviewers);
+
+        cmd!(
+            sh,
+            "gh pr edit {number} --title {title} --body {body} {base...} {labels...} {reviewers...}"
+        )
+        .read()?;
+        PullRequestInfo::view(&number)
+    }
+
+    /// Update the open pull request carrying the first label (or coming from the same branch
+    /// if there are no labels), create a new one otherwise
+    ///
+    /// The head branch of a pull request cannot be changed, so the release branch is expected
+    /// to be reused (force-pushed) between the runs
+    pub fn create_or_update(&self) -> Result<PullRequestInfo, PullRequestError> {
+        let existing = match self.labels.first() {
+            Some(label) => PullRequestInfo::find_open_by_label(label)?,
+            None => PullRequestInfo::find_open_by_head(&self.head)?,
+        };
+
+        match existing {
+            Some(existing) => self.update(existing.number),
+            None => self.create(),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use xshell::{cmd, Shell};
+
+use super::{GetFromGHError, GhResponse, Release, TagParseError, TagScheme};
+
+const PAGE_SIZE: usize = 100;
+
+/// Query for releases of the current repository
+///
+/// Unlike `gh release list`, all releases are returned unless [`ReleaseQuery::limit`] is set
+#[derive(Debug, Clone, Default)]
+pub struct ReleaseQuery {
+    limit: Option<usize>,
+    name: Option<String>,
+    exclude_drafts: bool,
+    exclude_prereleases: bool,
+    sort_by_version: bool,
+    skip_unparsed: bool,
+    tag_scheme: TagScheme,
+}
+
+impl ReleaseQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return at most `limit` releases (after filtering)
+    pub fn limit(&mut self, limit: usize) -> &mut Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Return all releases. This is the default
+    pub fn all(&mut self) -> &mut Self {
+        self.limit = None;
+        self
+    }
+
+    /// Only releases of the given crate
+    pub fn with_name<S>(&mut self, name: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn exclude_drafts(&mut self) -> &mut Self {
+        self.exclude_drafts = true;
+        self
+    }
+
+    pub fn exclude_prereleases(&mut self) -> &mut Self {
+        self.exclude_prereleases = true;
+        self
+    }
+
+    /// Highest version first, instead of the GitHub order (newest first)
+    pub fn sort_by_version(&mut self) -> &mut Self {
+        self.sort_by_version = true;
+        self
+    }
+
+    /// Drop tags not matching the tag scheme instead of returning them as errors
+    pub fn skip_unparsed(&mut self) -> &mut Self {
+        self.skip_unparsed = true;
+        self
+    }
+
+    pub fn with_tag_scheme(&mut self, scheme: TagScheme) -> &mut Self {
+        self.tag_scheme = scheme;
+        self
+    }
+
+    fn matches(&self, release: &Result<Release, TagParseError>) -> bool {
+        match release {
+            Ok(release) => {
+                self.name.as_ref().is_none_or(|name| &release.name == name)
+                    && !(self.exclude_drafts && release.draft)
+                    && !(self.exclude_prereleases && release.prelease)
+            }
+            // a tag that cannot be parsed cannot be checked against the name
+            Err(_) => !self.skip_unparsed && self.name.is_none(),
+        }
+    }
+
+    fn fetch_page(sh: &Shell, page: usize) -> Result<Vec<GhResponse>, GetFromGHError> {
+        let endpoint =
+            format!("repos/{{owner}}/{{repo}}/releases?per_page={PAGE_SIZE}&page={page}");
+        Ok(serde_json::from_str(
+            &cmd!(sh, "gh api {endpoint}").read()?,
+        )?)
+    }
+
+    pub fn fetch(&self) -> Result<Vec<Result<Release, TagParseError>>, GetFromGHError> {
+        let sh = Shell::new()?;
+        let mut result = Vec::new();
+
+        for page in 1.. {
+            let responses = Self::fetch_page(&sh, page)?;
+            let last_page = responses.len() < PAGE_SIZE;
+
+            result.extend(
+                responses
+                    .into_iter()
+                    .map(|x| Release::from_gh_response(x, &self.tag_scheme))
+                    .filter(|x| self.matches(x)),
+            );
+
+            // sorting needs every release, otherwise the order of GitHub is kept
+            let enough = !self.sort_by_version && self.limit.is_some_and(|x| result.len() >= x);
+            if last_page || enough {
+                break;
+            }
+        }
+
+        if self.sort_by_version {
+            result.sort_by(|a, b| match (a, b) {
+                (Ok(a), Ok(b)) => b.version.cmp(&a.version),
+                (Ok(_), Err(_)) => std::cmp::Ordering::Less,
+                (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
+                (Err(_), Err(_)) => std::cmp::Ordering::Equal,
+            });
+        }
+
+        if let Some(limit) = self.limit {
+            result.truncate(limit);
+        }
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,205 @@
+use semver::Version;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TagParseError {
+    #[error("Tag {0} does not match the tag scheme")]
+    SchemeMismatch(String),
+
+    #[error("Tag {tag} contains an invalid version: {error}")]
+    InvalidVersion { tag: String, error: semver::Error },
+}
+
+/// How release name and version are combined into a git tag
+#[derive(Debug, Clone, Default)]
+pub enum TagScheme {
+    /// `name-1.2.3`
+    #[default]
+    NameVersion,
+
+    /// `name-v1.2.3`
+    NameVVersion,
+
+    /// `name@1.2.3`
+    NameAtVersion,
+
+    /// `<prefix>1.2.3`, e.g. `v1.2.3`. The name is not part of the tag and is parsed as empty
+    Prefix(String),
+
+    /// Tags matching `regex`, which must contain a `version` and may contain a `name` named group.
+    /// Tags are created from `format`, in which `{name}` and `{version}` are substituted
+    Custom { regex: regex::Regex, format: String },
+}
+
+impl TagScheme {
+    pub fn v_prefix() -> Self {
+        Self::Prefix("v".to_string())
+    }
+
+    pub fn format(&self, name: &str, version: &Version) -> String {
+        match self {
+            Self::NameVersion => crate::versioned_name(name, &version.to_string()),
+            Self::NameVVersion => format!("{name}-v{version}"),
+            Self::NameAtVersion => format!("{name}@{version}"),
+            Self::Prefix(prefix) => format!("{prefix}{version}"),
+            Self::Custom { format, .. } => format
+                .replace("{name}", name)
+                .replace("{version}", &version.to_string()),
+        }
+    }
+
+    /// Split the tag into name and version
+    pub fn parse(&self, tag: &str) -> Result<(String, Version), TagParseError> {
+        let mismatch = || TagParseError::SchemeMismatch(tag.to_string());
+        let version = |version: &str| {
+            Version::parse(version).map_err(|error| TagParseError::InvalidVersion {
+                tag: tag.to_string(),
+                error,
+            })
+        };
+
+        match self {
+            Self::NameVersion => split_at_version(tag, "-"),
+            Self::NameVVersion => split_at_version(tag, "-v"),
+            Self::NameAtVersion => {
+                let (name, raw_version) = tag.rsplit_once('@').ok_or_else(mismatch)?;
+                Ok((name.to_string(), version(raw_version)?))
+            }
+            Self::Prefix(prefix) => {
+                let raw_version = tag.strip_prefix(prefix.as_str()).ok_or_else(mismatch)?;
+                Ok((String::new(), version(raw_version)?))
+            }
+            Self::Custom { regex, .. } => {
+                let captures = regex.captures(tag).ok_or_else(mismatch)?;
+                let raw_version = captures.name("version").ok_or_else(mismatch)?.as_str();
+                let name = captures
+                    .name("name")
+                    .map(|x| x.as_str())
+                    .unwrap_or_default();
+                Ok((name.to_string(), version(raw_version)?))
+            }
+        }
+    }
+}
+
+// Both names and prerelease versions may contain the separator (`foo-bar-1.0.0-rc-1`),
+// so the first split producing a valid version wins
+fn split_at_version(tag: &str, separator: &str) -> Result<(String, Version), TagParseError> {
+    let candidates = tag
+        .match_indices(separator)
+        .map(|(index, _)| (&tag[..index], &tag[index + separator.len()..]))
+        .collect::<Vec<_>>();
+
+    if let Some(result) = candidates
+        .iter()
+        .find_map(|(name, version)| Some((name.to_string(), Version::parse(version).ok()?)))
+    {
+        return Ok(result);
+    }
+
+    // report the version error for the part that looks like a version
+    let error = match candidates
+        .iter()
+        .find(|(_, version)| version.starts_with(|c: char| c.is_ascii_digit()))
+    {
+        Some((_, version)) => TagParseError::InvalidVersion {
+            tag: tag.to_string(),
+            error: Version::parse(version).unwrap_err(),
+        },
+        None => TagParseError::SchemeMismatch(tag.to_string()),
+    };
+    Err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(value: &str) -> Version {
+        Version::parse(value).unwrap()
+    }
+
+    fn round_trip(scheme: &TagScheme, name: &str, raw_version: &str, tag: &str) {
+        assert_eq!(scheme.format(name, &version(raw_version)), tag);
+        let (parsed_name, parsed_version) = scheme.parse(tag).unwrap();
+        assert_eq!(parsed_name, name);
+        assert_eq!(parsed_version, version(raw_version));
+    }
+
+    #[test]
+    fn name_version() {
+        let scheme = TagScheme::NameVersion;
+        round_trip(&scheme, "foo", "1.0.0", "foo-1.0.0");
+        round_trip(&scheme, "foo-bar", "1.2.3", "foo-bar-1.2.3");
+        round_trip(&scheme, "foo", "1.0.0-rc-1", "foo-1.0.0-rc-1");
+        round_trip(
+            &scheme,
+            "foo-bar",
+            "2.0.0-beta-2+build-5",
+            "foo-bar-2.0.0-beta-2+build-5",
+        );
+    }
+
+    #[test]
+    fn name_v_version() {
+        let scheme = TagScheme::NameVVersion;
+        round_trip(&scheme, "foo", "1.0.0", "foo-v1.0.0");
+        round_trip(&scheme, "foo-bar", "1.0.0-rc-1", "foo-bar-v1.0.0-rc-1");
+    }
+
+    #[test]
+    fn name_at_version() {
+        let scheme = TagScheme::NameAtVersion;
+        round_trip(&scheme, "foo", "1.0.0", "foo@1.0.0");
+        round_trip(
+            &scheme,
+            "@scope/foo-bar",
+            "1.0.0-rc-1",
+            "@scope/foo-bar@1.0.0-rc-1",
+        );
+    }
+
+    #[test]
+    fn prefix() {
+        let scheme = TagScheme::v_prefix();
+        round_trip(&scheme, "", "1.2.3", "v1.2.3");
+        round_trip(&scheme, "", "1.2.3-rc-1", "v1.2.3-rc-1");
+        assert!(matches!(
+            scheme.parse("1.2.3"),
+            Err(TagParseError::SchemeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn custom() {
+        let scheme = TagScheme::Custom {
+            regex: regex::Regex::new(r"^release/(?<name>.+)/(?<version>.+)$").unwrap(),
+            format: "release/{name}/{version}".to_string(),
+        };
+        round_trip(
+            &scheme,
+            "foo-bar",
+            "1.0.0-rc-1",
+            "release/foo-bar/1.0.0-rc-1",
+        );
+        assert!(matches!(
+            scheme.parse("foo-1.0.0"),
+            Err(TagParseError::SchemeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn v_prefixed_tag_is_not_a_name() {
+        assert!(matches!(
+            TagScheme::NameVersion.parse("v1.2.3"),
+            Err(TagParseError::SchemeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_version() {
+        assert!(matches!(
+            TagScheme::NameVersion.parse("foo-1.0"),
+            Err(TagParseError::InvalidVersion { .. })
+        ));
+    }
+}
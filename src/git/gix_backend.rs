@@ -0,0 +1,98 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+
+use super::{GitBackend, GitError, LastCommitError};
+
+fn gix_error<E>(error: E) -> GitError
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    GitError::GixError(Box::new(error))
+}
+
+/// Pure-Rust backend based on gitoxide. Does not require the `git` binary
+///
+/// The repository is opened once, so it is much cheaper than [`super::Cli`] when called in loops
+pub struct Gix(gix::ThreadSafeRepository);
+
+impl Gix {
+    /// Find the repository containing `dir`
+    pub fn discover<P>(dir: P) -> Result<Self, GitError>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self(
+            gix::ThreadSafeRepository::discover(dir).map_err(gix_error)?,
+        ))
+    }
+
+    /// Find the repository containing the current directory
+    pub fn from_current_dir() -> Result<Self, GitError> {
+        Self::discover(".")
+    }
+
+    fn repo(&self) -> gix::Repository {
+        self.0.to_thread_local()
+    }
+}
+
+impl GitBackend for Gix {
+    fn tags(&self) -> Result<Vec<String>, GitError> {
+        let repo = self.repo();
+        let references = repo.references().map_err(gix_error)?;
+
+        let mut tags = Vec::new();
+        for reference in references.tags().map_err(gix_error)? {
+            let reference = reference.map_err(GitError::GixError)?;
+            tags.push(reference.name().shorten().to_string());
+        }
+        tags.sort();
+        Ok(tags)
+    }
+
+    fn unstaged_changes(&self) -> Result<bool, GitError> {
+        // same semantics as `git status --porcelain`: untracked files count as changes
+        let status = self
+            .repo()
+            .status(gix::progress::Discard)
+            .map_err(gix_error)?
+            .untracked_files(gix::status::UntrackedFiles::Collapsed)
+            .into_iter(Vec::new())
+            .map_err(gix_error)?
+            .next()
+            .transpose()
+            .map_err(gix_error)?;
+        Ok(status.is_some())
+    }
+
+    fn last_commit_date(&self) -> Result<DateTime<Utc>, GitError> {
+        let time = self
+            .repo()
+            .head_commit()
+            .map_err(gix_error)?
+            .time()
+            .map_err(gix_error)?;
+        Ok(DateTime::from_timestamp(time.seconds, 0).ok_or(LastCommitError::NotATimestamp)?)
+    }
+
+    fn head_commit_hash(&self) -> Result<String, GitError> {
+        Ok(self.repo().head_id().map_err(gix_error)?.to_string())
+    }
+
+    fn root_path(&self) -> Result<PathBuf, GitError> {
+        let root = self.0.work_dir().ok_or(GitError::BareRepository)?;
+        gix::path::realpath(root).map_err(gix_error)
+    }
+
+    fn remote_url(&self, remote: &str) -> Result<String, GitError> {
+        let repo = self.repo();
+        let remote_ref = repo
+            .find_remote(remote)
+            .map_err(|_| GitError::MissingRemote(remote.to_string()))?;
+        let url = remote_ref
+            .url(gix::remote::Direction::Fetch)
+            .ok_or_else(|| GitError::MissingRemote(remote.to_string()))?;
+        Ok(url.to_bstring().to_string())
+    }
+}
@@ -0,0 +1,340 @@
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use xshell::{Shell, cmd};
+
+pub fn create_and_push_tag(tag: &str) -> Result<(), xshell::Error> {
+    let sh = Shell::new()?;
+
+    cmd!(sh, "git tag {tag}").run()?;
+    cmd!(sh, "git push origin {tag}").run()?;
+    Ok(())
+}
+
+pub fn has_tag(tag: &str) -> Result<bool, xshell::Error> {
+    let sh = Shell::new()?;
+
+    let tags = cmd!(sh, "git tag")
+        .read()?
+        .split('\n')
+        .map(|x| x.trim().to_string())
+        .collect::<Vec<_>>();
+
+    Ok(tags.iter().any(|x| x == tag))
+}
+
+pub fn unstaged_changes() -> Result<bool, xshell::Error> {
+    unstaged_changes_in(&Shell::new()?)
+}
+
+fn unstaged_changes_in(sh: &Shell) -> Result<bool, xshell::Error> {
+    Ok(!cmd!(sh, "git status --porcelain").read()?.is_empty())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LastCommitError {
+    #[error(transparent)]
+    XShellError(xshell::Error),
+    #[error("Could not parse timestamp value. Not an integer")]
+    ParseIntError,
+    #[error("Not a timestamp")]
+    NotATimestamp,
+}
+
+impl From<xshell::Error> for LastCommitError {
+    fn from(value: xshell::Error) -> Self {
+        LastCommitError::XShellError(value)
+    }
+}
+
+pub fn last_commit_date() -> Result<DateTime<Utc>, LastCommitError> {
+    last_commit_date_in(&Shell::new()?)
+}
+
+fn last_commit_date_in(sh: &Shell) -> Result<DateTime<Utc>, LastCommitError> {
+    DateTime::from_timestamp(
+        cmd!(sh, "git show --no-patch --format=%ct HEAD")
+            .read()?
+            .parse()
+            .map_err(|_| LastCommitError::ParseIntError)?,
+        0,
+    )
+    .ok_or_else(|| LastCommitError::NotATimestamp)
+}
+
+pub const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid {SOURCE_DATE_EPOCH} value: {0}")]
+pub struct InvalidSourceDateEpoch(pub String);
+
+/// Timestamp from `SOURCE_DATE_EPOCH`, `None` when the variable is not set
+pub fn source_date_epoch() -> Result<Option<DateTime<Utc>>, InvalidSourceDateEpoch> {
+    match std::env::var(SOURCE_DATE_EPOCH) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .ok()
+            .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
+            .map(Some)
+            .ok_or(InvalidSourceDateEpoch(value)),
+        Err(_) => Ok(None),
+    }
+}
+
+pub fn get_root_path() -> Result<PathBuf, xshell::Error> {
+    get_root_path_in(&Shell::new()?)
+}
+
+fn get_root_path_in(sh: &Shell) -> Result<PathBuf, xshell::Error> {
+    Ok(PathBuf::from(cmd!(sh, "git rev-parse --show-toplevel").read()?))
+}
+
+/// Resolve a path inside the git directory (e.g. `HEAD`, `refs/heads/main`), respecting worktrees
+pub fn git_path(path: &str) -> Result<PathBuf, xshell::Error> {
+    let sh = Shell::new()?;
+    let resolved = PathBuf::from(cmd!(sh, "git rev-parse --git-path {path}").read()?);
+    Ok(sh.current_dir().join(resolved))
+}
+
+/// Full hash of the HEAD commit
+pub fn head_commit_hash() -> Result<String, xshell::Error> {
+    head_commit_hash_in(&Shell::new()?)
+}
+
+fn head_commit_hash_in(sh: &Shell) -> Result<String, xshell::Error> {
+    cmd!(sh, "git rev-parse HEAD").read()
+}
+
+/// Name of the ref HEAD points to (e.g. `refs/heads/main`). `None` for a detached HEAD
+pub fn head_ref() -> Result<Option<String>, xshell::Error> {
+    let sh = Shell::new()?;
+    let head_ref = cmd!(sh, "git symbolic-ref -q HEAD")
+        .ignore_status()
+        .read()?;
+    Ok((!head_ref.is_empty()).then_some(head_ref))
+}
+
+pub struct OriginUrl(pub String);
+
+impl OriginUrl {
+    pub fn new() -> Result<OriginUrl, xshell::Error> {
+        let sh = Shell::new()?;
+        Ok(OriginUrl(cmd!(sh, "git remote get-url origin").read()?))
+    }
+
+    pub fn from_backend<B>(backend: &B) -> Result<OriginUrl, GitError>
+    where
+        B: GitBackend + ?Sized,
+    {
+        Ok(OriginUrl(backend.remote_url("origin")?))
+    }
+
+    pub fn to_http(mut self) -> Result<Self, ()> {
+        if self.0.starts_with("https://") || self.0.starts_with("http://") {
+            Ok(self)
+        } else if self.0.starts_with("git@") && self.0.contains("github") {
+            self.0 = self.0.replace("git@", "https://").replacen(":", "/", 1);
+            Ok(self)
+        } else {
+            Err(())
+        }
+    }
+
+    pub fn get(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum GitError {
+    #[error(transparent)]
+    XShellError(#[from] xshell::Error),
+
+    #[error(transparent)]
+    LastCommitError(#[from] LastCommitError),
+
+    #[error("Remote {0} does not exist or has no url")]
+    MissingRemote(String),
+
+    #[error("Repository has no working tree")]
+    BareRepository,
+
+    #[cfg(feature = "git-gix")]
+    #[error(transparent)]
+    GixError(Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// Read-only git operations, implemented either by the `git` binary ([`Cli`]) or gitoxide ([`Gix`])
+pub trait GitBackend {
+    /// Tag names, sorted by name
+    fn tags(&self) -> Result<Vec<String>, GitError>;
+    fn unstaged_changes(&self) -> Result<bool, GitError>;
+    fn last_commit_date(&self) -> Result<DateTime<Utc>, GitError>;
+    fn head_commit_hash(&self) -> Result<String, GitError>;
+    fn root_path(&self) -> Result<PathBuf, GitError>;
+    fn remote_url(&self, remote: &str) -> Result<String, GitError>;
+
+    fn has_tag(&self, tag: &str) -> Result<bool, GitError> {
+        Ok(self.tags()?.iter().any(|x| x == tag))
+    }
+}
+
+/// Default backend. Spawns `git` for every call, in the current directory by default
+#[derive(Debug, Default, Clone)]
+pub struct Cli {
+    dir: Option<PathBuf>,
+}
+
+impl Cli {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `git` in `dir` instead of the current directory
+    pub fn in_dir<P>(dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            dir: Some(dir.into()),
+        }
+    }
+
+    fn shell(&self) -> Result<Shell, xshell::Error> {
+        let sh = Shell::new()?;
+        if let Some(dir) = &self.dir {
+            sh.change_dir(dir);
+        }
+        Ok(sh)
+    }
+}
+
+impl GitBackend for Cli {
+    fn tags(&self) -> Result<Vec<String>, GitError> {
+        let sh = self.shell()?;
+        // sorted here, `tag.sort` may change the order of `git tag`
+        let mut tags = cmd!(sh, "git tag")
+            .read()?
+            .lines()
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>();
+        tags.sort();
+        Ok(tags)
+    }
+
+    fn unstaged_changes(&self) -> Result<bool, GitError> {
+        Ok(unstaged_changes_in(&self.shell()?)?)
+    }
+
+    fn last_commit_date(&self) -> Result<DateTime<Utc>, GitError> {
+        Ok(last_commit_date_in(&self.shell()?)?)
+    }
+
+    fn head_commit_hash(&self) -> Result<String, GitError> {
+        Ok(head_commit_hash_in(&self.shell()?)?)
+    }
+
+    fn root_path(&self) -> Result<PathBuf, GitError> {
+        Ok(get_root_path_in(&self.shell()?)?)
+    }
+
+    fn remote_url(&self, remote: &str) -> Result<String, GitError> {
+        let sh = self.shell()?;
+        let exists = cmd!(sh, "git remote")
+            .read()?
+            .lines()
+            .any(|x| x.trim() == remote);
+        if !exists {
+            return Err(GitError::MissingRemote(remote.to_string()));
+        }
+        Ok(cmd!(sh, "git remote get-url {remote}").read()?)
+    }
+}
+
+#[cfg(feature = "git-gix")]
+mod gix_backend;
+
+#[cfg(feature = "git-gix")]
+pub use gix_backend::Gix;
+
+#[cfg(all(test, feature = "git-gix"))]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    /// Repository with one commit, tags listed by version by `git tag` and an `origin` remote
+    fn repository() -> TempDir {
+        let dir = TempDir::new();
+        dir.write("README.md", "readme");
+
+        let sh = Shell::new().unwrap();
+        sh.change_dir(dir.path());
+        sh.set_var("GIT_CONFIG_GLOBAL", "/dev/null");
+        sh.set_var("GIT_CONFIG_NOSYSTEM", "1");
+        sh.set_var("GIT_AUTHOR_DATE", "2024-05-01T10:00:00Z");
+        sh.set_var("GIT_COMMITTER_DATE", "2024-05-01T10:00:00Z");
+        let git = |args: &str| {
+            let args = args.split_whitespace();
+            cmd!(sh, "git -c user.name=Test -c user.email=test@example.com {args...}")
+                .quiet()
+                .run()
+                .unwrap();
+        };
+        git("init -q");
+        git("config tag.sort -version:refname");
+        git("add README.md");
+        git("commit -q -m initial");
+        for tag in ["v1.2.0", "v1.10.0", "a-tag"] {
+            git(&format!("tag {tag}"));
+        }
+        git("tag -a -m release v2.0.0");
+        git("remote add origin https://example.com/owner/repo.git");
+        dir
+    }
+
+    #[test]
+    fn backends_agree() {
+        let dir = repository();
+        let backends: [Box<dyn GitBackend>; 2] = [
+            Box::new(Cli::in_dir(dir.path())),
+            Box::new(Gix::discover(dir.path()).unwrap()),
+        ];
+
+        for backend in &backends {
+            assert_eq!(
+                backend.tags().unwrap(),
+                ["a-tag", "v1.10.0", "v1.2.0", "v2.0.0"]
+            );
+            assert!(backend.has_tag("v2.0.0").unwrap());
+            assert_eq!(
+                backend.last_commit_date().unwrap().to_rfc3339(),
+                "2024-05-01T10:00:00+00:00"
+            );
+            assert!(!backend.unstaged_changes().unwrap());
+            assert_eq!(
+                backend.root_path().unwrap(),
+                dir.path().canonicalize().unwrap()
+            );
+            assert_eq!(
+                backend.remote_url("origin").unwrap(),
+                "https://example.com/owner/repo.git"
+            );
+            assert!(matches!(
+                backend.remote_url("upstream"),
+                Err(GitError::MissingRemote(x)) if x == "upstream"
+            ));
+        }
+        assert_eq!(
+            backends[0].head_commit_hash().unwrap(),
+            backends[1].head_commit_hash().unwrap()
+        );
+        assert_eq!(backends[0].head_commit_hash().unwrap().len(), 40);
+
+        dir.write("untracked.txt", "untracked");
+        for backend in &backends {
+            assert!(backend.unstaged_changes().unwrap());
+        }
+    }
+}
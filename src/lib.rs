@@ -1,13 +1,23 @@
+#[cfg(any(feature = "cargo", feature = "gh-cli"))]
 fn versioned_name(name: &str, version: &str) -> String {
     format!("{name}-{version}")
 }
 
+#[cfg(feature = "build-info")]
+pub mod build_info;
+
 #[cfg(feature = "cargo")]
 pub mod cargo;
 
 #[cfg(feature = "checksums")]
 pub mod checksums;
 
+#[cfg(any(feature = "checksums", feature = "targz"))]
+pub(crate) mod filter;
+
+#[cfg(feature = "forge")]
+pub mod forge;
+
 #[cfg(feature = "gh-cli")]
 pub mod gh_cli;
 
@@ -26,11 +36,17 @@ pub mod package_deb;
 #[cfg(feature = "package-rpm")]
 pub mod package_rpm;
 
-#[cfg(any(feature = "package-rpm", feature = "package-deb"))]
+#[cfg(any(feature = "package-rpm", feature = "package-deb", feature = "build-info"))]
 pub(crate) mod package_utils;
 
 #[cfg(feature = "git-precommit")]
 pub mod precommit;
 
+#[cfg(feature = "task-cache")]
+pub mod task_cache;
+
 #[cfg(feature = "targz")]
 pub mod targz;
+
+#[cfg(test)]
+mod test_utils;
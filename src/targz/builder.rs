@@ -0,0 +1,481 @@
+use std::path::{Path, PathBuf};
+
+use super::{reproducible_mtime, writer, ArchiveFormat, CompressError, Filter};
+
+#[derive(Debug, Clone)]
+enum Source {
+    File { path: PathBuf, mode: Option<u32> },
+    Dir { path: PathBuf, filter: Filter },
+    Data { contents: Vec<u8>, mode: u32 },
+}
+
+/// Entry to write, with the directories expanded
+enum Planned<'a> {
+    Path { path: PathBuf, mode: Option<u32> },
+    Data { contents: &'a [u8], mode: u32 },
+}
+
+/// `/` separated path of `path` relative to `root`
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|x| x.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Normalized `/` separated path inside the archive, below `prefix`. Leading slashes are dropped,
+/// `..` is rejected
+fn entry_name(prefix: Option<&str>, path: &str) -> Result<String, CompressError> {
+    let mut components = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => (),
+            ".." => return Err(CompressError::InvalidArchivePath(path.to_string())),
+            component => components.push(component),
+        }
+    }
+    if components.is_empty() {
+        return Err(CompressError::InvalidArchivePath(path.to_string()));
+    }
+
+    let relative = components.join("/");
+    Ok(match prefix {
+        Some(prefix) => format!("{prefix}/{relative}"),
+        None => relative,
+    })
+}
+
+/// Archive assembled from files of different locations, e.g. a release bundle
+///
+/// Entries are written in the order they were added. Every source is checked before the output
+/// file is created
+///
+/// ```ignore
+/// ArchiveBuilder::new()
+///     .with_prefix("app-1.0.0")
+///     .with_file_mode("target/x86_64-unknown-linux-gnu/release/app", "bin/app", 0o755)
+///     .with_file("README.md", "README.md")
+///     .with_dir("target/completions", "share/completions")
+///     .with_data("VERSION", "1.0.0\n", 0o644)
+///     .reproducible()
+///     .compress(Path::new("target/app-1.0.0.tar.gz"))?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveBuilder {
+    prefix: Option<String>,
+    entries: Vec<(String, Source)>,
+    reproducible: bool,
+    mtime: Option<u64>,
+    format: Option<ArchiveFormat>,
+    compression_level: Option<u32>,
+}
+
+impl ArchiveBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Directory all the entries are placed in, e.g. `name-version`
+    pub fn with_prefix(&mut self, prefix: &str) -> &mut Self {
+        let prefix = prefix.trim_matches('/');
+        self.prefix = (!prefix.is_empty()).then(|| prefix.to_string());
+        self
+    }
+
+    /// Add the file at `src` as `dest`, keeping its mode
+    pub fn with_file<S, D>(&mut self, src: S, dest: D) -> &mut Self
+    where
+        S: Into<PathBuf>,
+        D: Into<String>,
+    {
+        let source = Source::File {
+            path: src.into(),
+            mode: None,
+        };
+        self.entries.push((dest.into(), source));
+        self
+    }
+
+    /// Add the file at `src` as `dest` with the given mode, e.g. `0o755` for binaries
+    pub fn with_file_mode<S, D>(&mut self, src: S, dest: D, mode: u32) -> &mut Self
+    where
+        S: Into<PathBuf>,
+        D: Into<String>,
+    {
+        let source = Source::File {
+            path: src.into(),
+            mode: Some(mode),
+        };
+        self.entries.push((dest.into(), source));
+        self
+    }
+
+    /// Add every file below `src`, keeping the paths relative to it below `dest`
+    pub fn with_dir<S, D>(&mut self, src: S, dest: D) -> &mut Self
+    where
+        S: Into<PathBuf>,
+        D: Into<String>,
+    {
+        self.with_dir_filtered(src, dest, Filter::new())
+    }
+
+    /// Add the files below `src` accepted by the filter, keeping the paths relative to it below
+    /// `dest`
+    pub fn with_dir_filtered<S, D>(&mut self, src: S, dest: D, filter: Filter) -> &mut Self
+    where
+        S: Into<PathBuf>,
+        D: Into<String>,
+    {
+        let source = Source::Dir {
+            path: src.into(),
+            filter,
+        };
+        self.entries.push((dest.into(), source));
+        self
+    }
+
+    /// Add a generated file, e.g. a version file or a rendered manifest
+    pub fn with_data<D, C>(&mut self, dest: D, contents: C, mode: u32) -> &mut Self
+    where
+        D: Into<String>,
+        C: Into<Vec<u8>>,
+    {
+        let source = Source::Data {
+            contents: contents.into(),
+            mode,
+        };
+        self.entries.push((dest.into(), source));
+        self
+    }
+
+    /// Archives depending only on the contents, modes and mtimes of the inputs: uid/gid are
+    /// zeroed, owner names dropped, permissions normalized to `0o644`/`0o755`, mtimes newer than
+    /// [`reproducible_mtime`] clamped to it and the gzip header timestamp zeroed. Older mtimes
+    /// are kept, like `tar --clamp-mtime`, so files touched after the last commit, e.g. by a
+    /// fresh checkout or a build, give byte-for-byte identical archives. Explicit modes are kept
+    /// as they are
+    pub fn reproducible(&mut self) -> &mut Self {
+        self.reproducible = true;
+        self
+    }
+
+    /// Clamp mtimes to the unix timestamp instead of [`reproducible_mtime`]. Implies
+    /// [`ArchiveBuilder::reproducible`]
+    pub fn with_mtime(&mut self, mtime: u64) -> &mut Self {
+        self.reproducible = true;
+        self.mtime = Some(mtime);
+        self
+    }
+
+    /// Format of the archive. Guessed from the output file name by default, falling back to
+    /// tar.gz
+    pub fn with_format(&mut self, format: ArchiveFormat) -> &mut Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Clamped to [`ArchiveFormat::compression_levels`]. The default level of the format is
+    /// used otherwise
+    pub fn with_compression_level(&mut self, level: u32) -> &mut Self {
+        self.compression_level = Some(level);
+        self
+    }
+
+    fn plan(&self) -> Result<Vec<(String, Planned<'_>)>, CompressError> {
+        let prefix = self.prefix.as_deref();
+        let mut plan = Vec::new();
+
+        for (dest, source) in &self.entries {
+            match source {
+                Source::File { path, .. } if !path.is_file() => {
+                    return Err(CompressError::MissingSource(path.clone()));
+                }
+                Source::Dir { path, .. } if !path.is_dir() => {
+                    return Err(CompressError::MissingSource(path.clone()));
+                }
+                Source::Dir { path, filter } => {
+                    // symlinks to files are archived as the files they point to
+                    for entry in filter.walk(path)? {
+                        if entry.path().is_file() {
+                            let relative = relative_path(path, entry.path());
+                            let name = entry_name(prefix, &format!("{dest}/{relative}"))?;
+                            let path = entry.into_path();
+                            plan.push((name, Planned::Path { path, mode: None }));
+                        }
+                    }
+                }
+                Source::File { path, mode } => {
+                    let path = path.clone();
+                    let mode = *mode;
+                    plan.push((entry_name(prefix, dest)?, Planned::Path { path, mode }));
+                }
+                Source::Data { contents, mode } => {
+                    let mode = *mode;
+                    plan.push((entry_name(prefix, dest)?, Planned::Data { contents, mode }));
+                }
+            }
+        }
+        Ok(plan)
+    }
+
+    pub fn compress(&self, output_file: &Path) -> Result<(), CompressError> {
+        let plan = self.plan()?;
+
+        let mtime = match (self.reproducible, self.mtime) {
+            (_, Some(mtime)) => Some(mtime),
+            (true, None) => Some(reproducible_mtime()?),
+            (false, None) => None,
+        };
+
+        let format = self
+            .format
+            .or_else(|| ArchiveFormat::from_path(output_file))
+            .unwrap_or_default();
+        let mut archive =
+            writer::ArchiveWriter::create(output_file, format, self.compression_level, mtime)?;
+
+        for (name, entry) in plan {
+            match entry {
+                Planned::Path { path, mode } => archive.append_path(&path, &name, mode)?,
+                Planned::Data { contents, mode } => archive.append_data(&name, mode, contents)?,
+            }
+        }
+
+        archive.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+    use crate::targz::Archive;
+    use crate::test_utils::TempDir;
+
+    const MTIME: u64 = 1_000_000_000;
+
+    fn touch(path: &Path, time: SystemTime) {
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(time).unwrap();
+    }
+
+    #[test]
+    fn reproducible_archives_are_identical() {
+        let dir = TempDir::new();
+        dir.write("src/a.txt", "a");
+        dir.write("src/sub/b.txt", "b");
+        let mut builder = ArchiveBuilder::new();
+        builder
+            .with_prefix("app")
+            .with_dir(dir.join("src"), "")
+            .with_data("VERSION", "1.0.0\n", 0o644)
+            .with_mtime(MTIME);
+
+        for format in ArchiveFormat::ALL.into_iter().filter(|x| x.is_supported()) {
+            let first = dir.join(format!("first.{}", format.extension()));
+            builder.compress(&first).unwrap();
+            for file in ["src/a.txt", "src/sub/b.txt"] {
+                touch(&dir.join(file), SystemTime::now() + Duration::from_secs(60));
+            }
+            let second = dir.join(format!("second.{}", format.extension()));
+            builder.compress(&second).unwrap();
+
+            assert_eq!(
+                std::fs::read(first).unwrap(),
+                std::fs::read(second).unwrap(),
+                "{format:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn reproducible_archives_keep_older_mtimes() {
+        let dir = TempDir::new();
+        let old = dir.write("src/old.txt", "old");
+        dir.write("src/new.txt", "new");
+        touch(
+            &old,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(MTIME - 100),
+        );
+
+        let output = dir.join("app.tar.gz");
+        ArchiveBuilder::new()
+            .with_dir(dir.join("src"), "")
+            .with_mtime(MTIME)
+            .compress(&output)
+            .unwrap();
+
+        let mtimes = Archive::new(&output)
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|x| (x.path, x.mtime))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            mtimes,
+            [
+                ("new.txt".to_string(), MTIME),
+                ("old.txt".to_string(), MTIME - 100)
+            ]
+        );
+    }
+
+    fn modes(output: &Path) -> Vec<(String, u32)> {
+        let entries = Archive::new(output).entries().unwrap();
+        entries.into_iter().map(|x| (x.path, x.mode)).collect()
+    }
+
+    #[test]
+    fn rejects_duplicate_paths() {
+        let dir = TempDir::new();
+        let file = dir.write("src/a.txt", "a");
+        let output = dir.join("app.tar.gz");
+
+        let result = ArchiveBuilder::new()
+            .with_file(&file, "a.txt")
+            .with_data("/a.txt", "generated", 0o644)
+            .compress(&output);
+        assert!(matches!(result, Err(CompressError::DuplicatePath(x)) if x == "a.txt"));
+
+        let result = ArchiveBuilder::new()
+            .with_prefix("app")
+            .with_dir(dir.join("src"), "share")
+            .with_file(&file, "share/./a.txt")
+            .compress(&output);
+        assert!(matches!(result, Err(CompressError::DuplicatePath(x)) if x == "app/share/a.txt"));
+    }
+
+    #[test]
+    fn checks_sources_before_creating_output() {
+        let dir = TempDir::new();
+        let file = dir.write("a.txt", "a");
+        let output = dir.join("app.tar.gz");
+
+        let result = ArchiveBuilder::new()
+            .with_file(&file, "a.txt")
+            .with_file(dir.join("missing.txt"), "missing.txt")
+            .compress(&output);
+        assert!(
+            matches!(result, Err(CompressError::MissingSource(x)) if x == dir.join("missing.txt"))
+        );
+        let result = ArchiveBuilder::new()
+            .with_dir(dir.join("missing"), "")
+            .compress(&output);
+        assert!(matches!(result, Err(CompressError::MissingSource(x)) if x == dir.join("missing")));
+        let result = ArchiveBuilder::new()
+            .with_file(&file, "../a.txt")
+            .compress(&output);
+        assert!(matches!(result, Err(CompressError::InvalidArchivePath(_))));
+
+        assert!(!output.exists());
+    }
+
+    #[test]
+    fn normalizes_entry_names() {
+        assert_eq!(entry_name(None, "/bin//./app/").unwrap(), "bin/app");
+        assert_eq!(
+            entry_name(Some("app-1.0"), "./README.md").unwrap(),
+            "app-1.0/README.md"
+        );
+        for path in ["", "/", "./.", "../a", "bin/../../a", "bin/.."] {
+            assert!(
+                matches!(entry_name(None, path), Err(CompressError::InvalidArchivePath(x)) if x == path),
+                "{path}"
+            );
+        }
+
+        let dir = TempDir::new();
+        let file = dir.write("src/a.txt", "a");
+        dir.write("src/sub/b.txt", "b");
+        let output = dir.join("app.tar.gz");
+        ArchiveBuilder::new()
+            .with_prefix("/app-1.0/")
+            .with_file(&file, "/docs/a.txt")
+            .with_dir(dir.join("src"), ".")
+            .compress(&output)
+            .unwrap();
+
+        let paths = modes(&output).into_iter().map(|x| x.0).collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            ["app-1.0/docs/a.txt", "app-1.0/a.txt", "app-1.0/sub/b.txt"]
+        );
+    }
+
+    #[test]
+    fn writes_data_entries() {
+        let dir = TempDir::new();
+        let output = dir.join("app.tar.gz");
+        ArchiveBuilder::new()
+            .with_prefix("app")
+            .with_data("VERSION", "1.0.0\n", 0o600)
+            .with_data("bin/run", "#!/bin/sh\n", 0o755)
+            .with_mtime(MTIME)
+            .compress(&output)
+            .unwrap();
+
+        let entries = Archive::new(&output).entries().unwrap();
+        let summary = entries
+            .iter()
+            .map(|x| (x.path.as_str(), x.size, x.mode, x.mtime))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("app/VERSION", 6, 0o600, MTIME),
+                ("app/bin/run", 10, 0o755, MTIME)
+            ]
+        );
+
+        let extracted = dir.join("extracted");
+        Archive::new(&output).extract(&extracted).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(extracted.join("app/VERSION")).unwrap(),
+            "1.0.0\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn explicit_modes_override_normalized_modes() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new();
+        let private = dir.write("private.txt", "private");
+        let script = dir.write("script.sh", "#!/bin/sh\n");
+        std::fs::set_permissions(&private, std::fs::Permissions::from_mode(0o600)).unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o700)).unwrap();
+
+        let mut builder = ArchiveBuilder::new();
+        builder
+            .with_file(&private, "normalized.txt")
+            .with_file(&script, "normalized.sh")
+            .with_file_mode(&private, "explicit.txt", 0o600)
+            .with_file_mode(&private, "bin/app", 0o755)
+            .with_file_mode(&script, "explicit.sh", 0o500)
+            .reproducible()
+            .with_mtime(MTIME);
+
+        for format in [ArchiveFormat::TarGz, ArchiveFormat::Zip] {
+            if !format.is_supported() {
+                continue;
+            }
+            let output = dir.join(format!("app.{}", format.extension()));
+            builder.compress(&output).unwrap();
+            assert_eq!(
+                modes(&output),
+                [
+                    ("normalized.txt".to_string(), 0o644),
+                    ("normalized.sh".to_string(), 0o755),
+                    ("explicit.txt".to_string(), 0o600),
+                    ("bin/app".to_string(), 0o755),
+                    ("explicit.sh".to_string(), 0o500),
+                ],
+                "{format:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::path::Path;
+
+/// Archive format written by [`DirCompress`](super::DirCompress). Formats other than tar.gz
+/// need their `targz-*` feature
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ArchiveFormat {
+    #[default]
+    TarGz,
+    TarXz,
+    TarZst,
+    TarBz2,
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub const ALL: [Self; 5] = [
+        Self::TarGz,
+        Self::TarXz,
+        Self::TarZst,
+        Self::TarBz2,
+        Self::Zip,
+    ];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::TarGz => "tar.gz",
+            Self::TarXz => "tar.xz",
+            Self::TarZst => "tar.zst",
+            Self::TarBz2 => "tar.bz2",
+            Self::Zip => "zip",
+        }
+    }
+
+    /// Format matching the file name, e.g. `tar.zst`. The short forms `tgz`, `txz`, `tzst` and
+    /// `tbz2` are recognized as well
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        let short = [
+            ("tgz", Self::TarGz),
+            ("txz", Self::TarXz),
+            ("tzst", Self::TarZst),
+            ("tbz2", Self::TarBz2),
+        ];
+
+        Self::ALL
+            .iter()
+            .map(|format| (format.extension(), *format))
+            .chain(short)
+            .find(|(extension, _)| name.ends_with(&format!(".{extension}")))
+            .map(|(_, format)| format)
+    }
+
+    /// Cargo feature the format needs
+    pub fn feature(&self) -> &'static str {
+        match self {
+            Self::TarGz => "targz",
+            Self::TarXz => "targz-xz",
+            Self::TarZst => "targz-zstd",
+            Self::TarBz2 => "targz-bzip2",
+            Self::Zip => "targz-zip",
+        }
+    }
+
+    /// Whether the feature of the format is enabled
+    pub fn is_supported(&self) -> bool {
+        match self {
+            Self::TarGz => true,
+            Self::TarXz => cfg!(feature = "targz-xz"),
+            Self::TarZst => cfg!(feature = "targz-zstd"),
+            Self::TarBz2 => cfg!(feature = "targz-bzip2"),
+            Self::Zip => cfg!(feature = "targz-zip"),
+        }
+    }
+
+    /// Levels supported by the format. Requested levels are clamped to it
+    pub fn compression_levels(&self) -> std::ops::RangeInclusive<u32> {
+        match self {
+            Self::TarGz | Self::TarXz | Self::Zip => 0..=9,
+            Self::TarZst => 1..=22,
+            Self::TarBz2 => 1..=9,
+        }
+    }
+}
+
+impl std::fmt::Display for ArchiveFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.extension())
+    }
+}
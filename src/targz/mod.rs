@@ -1,7 +1,61 @@
 use std::path::{Path, PathBuf};
 
+use crate::git::LastCommitError;
+
+mod builder;
+mod format;
+mod reader;
+mod writer;
+
+pub use crate::filter::Filter;
+pub use builder::ArchiveBuilder;
+pub use format::ArchiveFormat;
+pub use reader::{Archive, ArchiveDiff, ArchiveEntry, EntryChange, EntryKind};
+pub use writer::reproducible_mtime;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CompressError {
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+
+    #[error(transparent)]
+    CommitDateError(#[from] LastCommitError),
+
+    #[cfg(feature = "targz-zip")]
+    #[error(transparent)]
+    ZipError(#[from] zip::result::ZipError),
+
+    #[error("Duplicate path in archive: {0}")]
+    DuplicatePath(String),
+
+    #[error("Archive source not found: {0}")]
+    MissingSource(PathBuf),
+
+    #[error("Invalid path in archive: {0}")]
+    InvalidArchivePath(String),
+
+    #[error("Archive entry escapes the extraction directory: {0}")]
+    UnsafePath(String),
+
+    #[error(transparent)]
+    InvalidSourceDateEpoch(#[from] crate::git::InvalidSourceDateEpoch),
+
+    #[error("{0} archives require the `{feature}` feature", feature = .0.feature())]
+    UnsupportedFormat(ArchiveFormat),
+}
+
+/// Archives the files of a directory
+///
+/// Without any filter every file is archived. The name filters ([`DirCompress::filter_filename`],
+/// [`DirCompress::filter_extension`], [`DirCompress::filter_filename_regex`]) and the
+/// [`DirCompress::include`] globs are OR-ed: once any of them is given, a file has to match one.
+/// Excludes, ignore files and the predicates of the [`Filter`] are AND-ed on top, so
+/// `include("dist/**").exclude("*.pdb")` takes the dist directory minus the debug files
 pub struct DirCompress {
     dir: PathBuf,
+    /// Prefix, timestamps, format and level. Entries are only added by `compress`
+    archive: ArchiveBuilder,
+    filter: Filter,
     filter_extensions: Vec<String>,
     filter_filename_regex: Option<regex::Regex>,
     filter_filenames: Vec<String>,
@@ -13,6 +67,8 @@ impl DirCompress {
         let dir : PathBuf = dir.into();
         dir.is_dir().then_some(Self {
             dir,
+            archive: ArchiveBuilder::new(),
+            filter: Filter::new(),
             filter_filenames: Vec::new(),
             filter_extensions: Vec::new(),
             filter_filename_regex: None,
@@ -20,11 +76,68 @@ impl DirCompress {
         })
     }
 
+    /// Include files from subdirectories too, keeping their paths relative to the directory.
+    /// Implied by [`DirCompress::include`] globs
     pub fn search_subdirs(&mut self) -> &mut Self {
         self.search_subdirs = true;
         self
     }
 
+    /// See [`ArchiveBuilder::with_prefix`]
+    pub fn with_prefix(&mut self, prefix: &str) -> &mut Self {
+        self.archive.with_prefix(prefix);
+        self
+    }
+
+    /// See [`ArchiveBuilder::reproducible`]
+    pub fn reproducible(&mut self) -> &mut Self {
+        self.archive.reproducible();
+        self
+    }
+
+    /// See [`ArchiveBuilder::with_mtime`]
+    pub fn with_mtime(&mut self, mtime: u64) -> &mut Self {
+        self.archive.with_mtime(mtime);
+        self
+    }
+
+    /// See [`ArchiveBuilder::with_format`]
+    pub fn with_format(&mut self, format: ArchiveFormat) -> &mut Self {
+        self.archive.with_format(format);
+        self
+    }
+
+    /// See [`ArchiveBuilder::with_compression_level`]
+    pub fn with_compression_level(&mut self, level: u32) -> &mut Self {
+        self.archive.with_compression_level(level);
+        self
+    }
+
+    /// Replace the filter. The name filters still apply on top of it
+    pub fn with_filter(&mut self, filter: Filter) -> &mut Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Take the files matching the glob, relative to the directory in the gitignore syntax.
+    /// Subdirectories are searched as well, `/*.txt` only matches the top level
+    pub fn include(&mut self, glob: &str) -> &mut Self {
+        self.filter = std::mem::take(&mut self.filter).include(glob);
+        self
+    }
+
+    /// Skip the files and directories matching the glob, even when they are included
+    pub fn exclude(&mut self, glob: &str) -> &mut Self {
+        self.filter = std::mem::take(&mut self.filter).exclude(glob);
+        self
+    }
+
+    /// Skip the files ignored by `.gitignore` and the other ignore files, see [`Filter::git_ignore`]
+    pub fn git_ignore(&mut self) -> &mut Self {
+        self.filter = std::mem::take(&mut self.filter).git_ignore();
+        self
+    }
+
     pub fn filter_extension(&mut self, extension: &str) -> &mut Self {
         self.filter_extensions.push(extension.to_string());
         self
@@ -40,60 +153,162 @@ impl DirCompress {
         self
     }
 
-    pub fn compress(&self, output_file: &Path) -> Result<(), std::io::Error> {
-        let walkdir = walkdir::WalkDir::new(&self.dir);
-        let walkdir = if self.search_subdirs {
-            walkdir.max_depth(1)
-        } else {
-            walkdir
-        };
+    pub fn compress(&self, output_file: &Path) -> Result<(), CompressError> {
+        let mut filter = self.filter.clone();
+        if !self.search_subdirs && !self.filter.has_include_globs() {
+            let root = self.dir.clone();
+            filter = filter.predicate(move |path| path.parent() == Some(root.as_path()));
+        }
 
-        let files = walkdir
-            .into_iter()
-            .filter_map(|x| {
-                x.ok()
-                    .and_then(|x| {
-                        let filename = x.file_name().to_string_lossy();
-
-                        for name in &self.filter_filenames {
-                            if filename == name.as_str() {
-                                return Some(x);
-                            }
-                        }
-
-                        for ext in &self.filter_extensions {
-                            if x.file_name().to_string_lossy().ends_with(ext) {
-                                return Some(x);
-                            }
-                        }
-
-                        if let Some(regex) = &self.filter_filename_regex {
-                            if regex.is_match(filename.as_ref()) {
-                                return Some(x);
-                            }
-                        }
-
-                        None
-                    })
-                    .and_then(|x| x.path().is_file().then_some(x))
-            })
-            .map(|x| x.path().to_path_buf())
-            .collect::<Vec<PathBuf>>();
-
-        let dest_file = std::fs::File::create(&output_file)?;
-        let enc = flate2::write::GzEncoder::new(&dest_file, flate2::Compression::default());
-        let mut builder = tar::Builder::new(enc);
-
-        for src in files {
-            builder.append_path_with_name(
-                &src,
-                src.file_name()
-                    .unwrap_or_default()
-                    .to_string_lossy()
-                    .to_string(),
-            )?;
+        let has_name_filters = !self.filter_filenames.is_empty()
+            || !self.filter_extensions.is_empty()
+            || self.filter_filename_regex.is_some();
+        if has_name_filters {
+            let filenames = self.filter_filenames.clone();
+            let extensions = self.filter_extensions.clone();
+            let regex = self.filter_filename_regex.clone();
+            filter = filter.include_if(move |path| {
+                let filename = path
+                    .file_name()
+                    .map(|x| x.to_string_lossy())
+                    .unwrap_or_default();
+                filenames.iter().any(|x| filename == x.as_str())
+                    || extensions.iter().any(|x| filename.ends_with(x.as_str()))
+                    || regex.as_ref().is_some_and(|x| x.is_match(&filename))
+            });
+        }
+
+        let mut archive = self.archive.clone();
+        archive.with_dir_filtered(self.dir.clone(), "", filter);
+        archive.compress(output_file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    /// Paths archived from a small tree with top-level files, `dist` and `docs`
+    fn archived<F>(configure: F) -> Vec<String>
+    where
+        F: FnOnce(&mut DirCompress),
+    {
+        let dir = TempDir::new();
+        for file in [
+            "tree/a.txt",
+            "tree/b.md",
+            "tree/notes.pdb",
+            "tree/dist/app",
+            "tree/dist/app.pdb",
+            "tree/dist/sub/lib.so",
+            "tree/docs/guide.md",
+        ] {
+            dir.write(file, file);
         }
 
-        Ok(())
+        let output = dir.join("out.tar.gz");
+        let mut compress = DirCompress::new(dir.join("tree")).unwrap();
+        configure(&mut compress);
+        compress.compress(&output).unwrap();
+
+        let mut paths = Archive::new(&output)
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|x| x.path)
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn archives_top_level_by_default() {
+        assert_eq!(archived(|_| ()), ["a.txt", "b.md", "notes.pdb"]);
+    }
+
+    #[test]
+    fn searches_subdirs() {
+        assert_eq!(
+            archived(|x| {
+                x.search_subdirs();
+            }),
+            [
+                "a.txt",
+                "b.md",
+                "dist/app",
+                "dist/app.pdb",
+                "dist/sub/lib.so",
+                "docs/guide.md",
+                "notes.pdb"
+            ]
+        );
+    }
+
+    #[test]
+    fn include_globs_search_subdirs() {
+        assert_eq!(
+            archived(|x| {
+                x.include("dist/**");
+            }),
+            ["dist/app", "dist/app.pdb", "dist/sub/lib.so"]
+        );
+        assert_eq!(
+            archived(|x| {
+                x.include("*.md");
+            }),
+            ["b.md", "docs/guide.md"]
+        );
+        assert_eq!(
+            archived(|x| {
+                x.include("/*.md");
+            }),
+            ["b.md"]
+        );
+    }
+
+    #[test]
+    fn excludes_apply_on_top_of_includes() {
+        assert_eq!(
+            archived(|x| {
+                x.include("dist/**").exclude("*.pdb");
+            }),
+            ["dist/app", "dist/sub/lib.so"]
+        );
+        assert_eq!(
+            archived(|x| {
+                x.search_subdirs().filter_extension(".pdb").exclude("dist");
+            }),
+            ["notes.pdb"]
+        );
+    }
+
+    #[test]
+    fn name_filters_are_ored_with_globs() {
+        assert_eq!(
+            archived(|x| {
+                x.filter_extension(".md").filter_filename("a.txt");
+            }),
+            ["a.txt", "b.md"]
+        );
+        assert_eq!(
+            archived(|x| {
+                x.include("dist/**").filter_extension(".md");
+            }),
+            [
+                "b.md",
+                "dist/app",
+                "dist/app.pdb",
+                "dist/sub/lib.so",
+                "docs/guide.md"
+            ]
+        );
+        assert_eq!(
+            archived(|x| {
+                x.include("/a.txt")
+                    .filter_filename_regex(regex::Regex::new("^lib").unwrap());
+            }),
+            ["a.txt", "dist/sub/lib.so"]
+        );
     }
 }
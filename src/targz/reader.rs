@@ -0,0 +1,634 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+use super::{ArchiveFormat, CompressError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+    /// Hard links, devices, fifos
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// `/` separated, without a leading `./` or a trailing `/`
+    pub path: String,
+    pub kind: EntryKind,
+    pub size: u64,
+    pub mode: u32,
+    pub mtime: u64,
+    /// CRC-32 of the contents of files, `0` for other entries
+    pub crc32: u32,
+    /// Target of symlinks and hard links
+    pub link: Option<String>,
+}
+
+impl ArchiveEntry {
+    /// Same kind, contents, mode and link target. Times are ignored
+    pub fn same_contents(&self, other: &Self) -> bool {
+        self.kind == other.kind
+            && self.size == other.size
+            && self.mode == other.mode
+            && self.crc32 == other.crc32
+            && self.link == other.link
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryChange {
+    pub path: String,
+    pub old: ArchiveEntry,
+    pub new: ArchiveEntry,
+}
+
+/// Differences between two archives, see [`Archive::diff`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchiveDiff {
+    /// Only in the new archive
+    pub added: Vec<String>,
+    /// Only in the old archive
+    pub removed: Vec<String>,
+    pub changed: Vec<EntryChange>,
+}
+
+impl ArchiveDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Normalized entry path, `None` for absolute paths and paths containing `..`
+fn safe_path(path: &Path) -> Option<String> {
+    let mut components = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(x) => components.push(x.to_string_lossy()),
+            Component::CurDir => (),
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(components.join("/"))
+}
+
+/// Components below `root` that `target` resolves to from the directory `base`, following the
+/// symlinks extracted so far. `None` once the resolution leaves `root`.
+///
+/// `..` is only followed after a real directory or one of the directories in `created`: a
+/// component that does not exist yet could still be extracted as a link afterwards
+fn resolve_below(
+    root: &Path,
+    base: Vec<OsString>,
+    target: &Path,
+    created: &[OsString],
+    hops: &mut u32,
+) -> Option<Vec<OsString>> {
+    let mut resolved = base;
+    for component in target.components() {
+        match component {
+            Component::Normal(name) => {
+                resolved.push(name.to_os_string());
+                let path = resolved.iter().fold(root.to_path_buf(), |x, y| x.join(y));
+                if let Ok(link) = std::fs::read_link(&path) {
+                    // symlink loops
+                    *hops = hops.checked_sub(1)?;
+                    resolved.pop();
+                    resolved = resolve_below(root, resolved, &link, created, hops)?;
+                }
+            }
+            Component::CurDir => (),
+            Component::ParentDir => {
+                let path = resolved.iter().fold(root.to_path_buf(), |x, y| x.join(y));
+                let is_dir = std::fs::symlink_metadata(&path).is_ok_and(|x| x.is_dir());
+                if !is_dir && !created.starts_with(&resolved) {
+                    return None;
+                }
+                resolved.pop()?;
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(resolved)
+}
+
+/// Whether a link extracted to `root/path` pointing to `target` stays inside `root`, also when
+/// it goes through links extracted before (`a -> s/..` with `s -> .`) or after (`a -> b/..`
+/// followed by `b -> .`)
+fn is_contained_link(root: &Path, path: &str, target: &Path) -> bool {
+    let mut hops = 40;
+    let parent = Path::new(path).parent().unwrap_or(Path::new(""));
+    // the parents of the link are created as directories when it is extracted
+    resolve_below(root, Vec::new(), parent, &[], &mut hops)
+        .and_then(|base| resolve_below(root, base.clone(), target, &base, &mut hops))
+        .is_some()
+}
+
+/// Existing archive of any supported format
+#[derive(Debug, Clone)]
+pub struct Archive {
+    path: PathBuf,
+    format: ArchiveFormat,
+}
+
+impl Archive {
+    /// The format is guessed from the file name, falling back to tar.gz
+    pub fn new<P>(path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        let format = ArchiveFormat::from_path(&path).unwrap_or_default();
+        Self { path, format }
+    }
+
+    pub fn with_format(&mut self, format: ArchiveFormat) -> &mut Self {
+        self.format = format;
+        self
+    }
+
+    fn tar(&self) -> Result<tar::Archive<Box<dyn Read>>, CompressError> {
+        if !self.format.is_supported() {
+            return Err(CompressError::UnsupportedFormat(self.format));
+        }
+
+        let file = File::open(&self.path)?;
+        let decoder: Box<dyn Read> = match self.format {
+            ArchiveFormat::TarGz => Box::new(flate2::read::GzDecoder::new(file)),
+            #[cfg(feature = "targz-xz")]
+            ArchiveFormat::TarXz => Box::new(xz2::read::XzDecoder::new(file)),
+            #[cfg(feature = "targz-zstd")]
+            ArchiveFormat::TarZst => Box::new(zstd::Decoder::new(file)?),
+            #[cfg(feature = "targz-bzip2")]
+            ArchiveFormat::TarBz2 => Box::new(bzip2::read::BzDecoder::new(file)),
+            _ => return Err(CompressError::UnsupportedFormat(self.format)),
+        };
+        Ok(tar::Archive::new(decoder))
+    }
+
+    #[cfg(feature = "targz-zip")]
+    fn zip(&self) -> Result<zip::ZipArchive<File>, CompressError> {
+        Ok(zip::ZipArchive::new(File::open(&self.path)?)?)
+    }
+
+    /// Entries in the archive order. Paths escaping the archive are reported as
+    /// [`CompressError::UnsafePath`]
+    pub fn entries(&self) -> Result<Vec<ArchiveEntry>, CompressError> {
+        #[cfg(feature = "targz-zip")]
+        if self.format == ArchiveFormat::Zip {
+            return self.zip_entries();
+        }
+
+        let mut archive = self.tar()?;
+        let mut entries = Vec::new();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let header = entry.header();
+            let path = entry.path()?;
+            let path = safe_path(&path)
+                .ok_or_else(|| CompressError::UnsafePath(path.display().to_string()))?;
+
+            let entry_type = header.entry_type();
+            let kind = if entry_type.is_file() {
+                EntryKind::File
+            } else if entry_type.is_dir() {
+                EntryKind::Directory
+            } else if entry_type.is_symlink() {
+                EntryKind::Symlink
+            } else {
+                EntryKind::Other
+            };
+            let link = entry.link_name()?.map(|x| x.to_string_lossy().into_owned());
+            let (size, mode, mtime) = (header.size()?, header.mode()?, header.mtime()?);
+
+            let mut crc = flate2::Crc::new();
+            if kind == EntryKind::File {
+                let mut buffer = vec![0; 64 * 1024];
+                loop {
+                    let count = entry.read(&mut buffer)?;
+                    if count == 0 {
+                        break;
+                    }
+                    crc.update(&buffer[..count]);
+                }
+            }
+
+            entries.push(ArchiveEntry {
+                path,
+                kind,
+                size,
+                mode: mode & 0o7777,
+                mtime,
+                crc32: crc.sum(),
+                link,
+            });
+        }
+        Ok(entries)
+    }
+
+    #[cfg(feature = "targz-zip")]
+    fn zip_entries(&self) -> Result<Vec<ArchiveEntry>, CompressError> {
+        let mut archive = self.zip()?;
+        let mut entries = Vec::new();
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i)?;
+            let path = safe_path(Path::new(file.name()))
+                .ok_or_else(|| CompressError::UnsafePath(file.name().to_string()))?;
+
+            let kind = if file.is_dir() {
+                EntryKind::Directory
+            } else if file.is_symlink() {
+                EntryKind::Symlink
+            } else {
+                EntryKind::File
+            };
+            let link = match kind {
+                EntryKind::Symlink => {
+                    let mut target = String::new();
+                    file.read_to_string(&mut target)?;
+                    Some(target)
+                }
+                _ => None,
+            };
+            let mode = file.unix_mode().map_or(0o644, |x| x & 0o7777);
+
+            entries.push(ArchiveEntry {
+                path,
+                kind,
+                size: file.size(),
+                mode,
+                mtime: file.last_modified().map_or(0, zip_timestamp),
+                crc32: if kind == EntryKind::File {
+                    file.crc32()
+                } else {
+                    0
+                },
+                link,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Extract into `dest`, which is created if needed. Absolute paths, paths containing `..`
+    /// and links pointing out of `dest` fail with [`CompressError::UnsafePath`] before anything
+    /// is written below them. Files replace links extracted before instead of being written
+    /// through them
+    pub fn extract(&self, dest: &Path) -> Result<(), CompressError> {
+        std::fs::create_dir_all(dest)?;
+
+        #[cfg(feature = "targz-zip")]
+        if self.format == ArchiveFormat::Zip {
+            return self.zip_extract(dest);
+        }
+
+        let mut archive = self.tar()?;
+        archive.set_preserve_permissions(true);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.into_owned();
+            let unsafe_path = || CompressError::UnsafePath(path.display().to_string());
+
+            let name = safe_path(&path).ok_or_else(unsafe_path)?;
+            if let Some(target) = entry.link_name()? {
+                let contained = match entry.header().entry_type() {
+                    // hard link targets are relative to the archive root
+                    tar::EntryType::Link => safe_path(&target).is_some(),
+                    _ => is_contained_link(dest, &name, &target),
+                };
+                if !contained {
+                    return Err(unsafe_path());
+                }
+            }
+
+            // also refuses to write through symlinks extracted before
+            if !entry.unpack_in(dest)? {
+                return Err(unsafe_path());
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "targz-zip")]
+    fn zip_extract(&self, dest: &Path) -> Result<(), CompressError> {
+        let mut archive = self.zip()?;
+        let root = dest.canonicalize()?;
+
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i)?;
+            let unsafe_path = || CompressError::UnsafePath(file.name().to_string());
+            let name = safe_path(Path::new(file.name())).ok_or_else(unsafe_path)?;
+            let path = dest.join(&name);
+
+            if file.is_dir() {
+                std::fs::create_dir_all(&path)?;
+                if !path.canonicalize()?.starts_with(&root) {
+                    return Err(unsafe_path());
+                }
+                continue;
+            }
+
+            let parent = path.parent().unwrap_or(dest);
+            std::fs::create_dir_all(parent)?;
+            if !parent.canonicalize()?.starts_with(&root) {
+                return Err(unsafe_path());
+            }
+
+            // never write through a link extracted before, like the tar crate
+            if std::fs::symlink_metadata(&path).is_ok_and(|x| !x.is_dir()) {
+                std::fs::remove_file(&path)?;
+            }
+
+            if file.is_symlink() {
+                let mut target = String::new();
+                file.read_to_string(&mut target)?;
+                if !is_contained_link(dest, &name, Path::new(&target)) {
+                    return Err(CompressError::UnsafePath(name));
+                }
+                symlink(Path::new(&target), &path)?;
+                continue;
+            }
+
+            let mut output = std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)?;
+            std::io::copy(&mut file, &mut output)?;
+            set_mode(&path, file.unix_mode())?;
+        }
+        Ok(())
+    }
+
+    /// Entries added, removed or changed in `new` compared to this archive, see
+    /// [`ArchiveEntry::same_contents`]
+    pub fn diff(&self, new: &Archive) -> Result<ArchiveDiff, CompressError> {
+        let by_path = |entries: Vec<ArchiveEntry>| {
+            entries
+                .into_iter()
+                .map(|x| (x.path.clone(), x))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let old = by_path(self.entries()?);
+        let mut new = by_path(new.entries()?);
+
+        let mut diff = ArchiveDiff::default();
+        for (path, old) in old {
+            match new.remove(&path) {
+                None => diff.removed.push(path),
+                Some(new) if !old.same_contents(&new) => {
+                    diff.changed.push(EntryChange { path, old, new })
+                }
+                Some(_) => (),
+            }
+        }
+        diff.added = new.into_keys().collect();
+        Ok(diff)
+    }
+}
+
+#[cfg(all(feature = "targz-zip", unix))]
+fn symlink(target: &Path, path: &Path) -> Result<(), std::io::Error> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+/// Without symlink support the target is written as the file contents
+#[cfg(all(feature = "targz-zip", not(unix)))]
+fn symlink(target: &Path, path: &Path) -> Result<(), std::io::Error> {
+    std::fs::write(path, target.to_string_lossy().as_bytes())
+}
+
+#[cfg(all(feature = "targz-zip", unix))]
+fn set_mode(path: &Path, mode: Option<u32>) -> Result<(), std::io::Error> {
+    use std::os::unix::fs::PermissionsExt;
+    match mode {
+        Some(mode) => std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o777)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(all(feature = "targz-zip", not(unix)))]
+fn set_mode(_path: &Path, _mode: Option<u32>) -> Result<(), std::io::Error> {
+    Ok(())
+}
+
+/// Zip timestamps are read as UTC, like they are written
+#[cfg(feature = "targz-zip")]
+fn zip_timestamp(time: zip::DateTime) -> u64 {
+    chrono::NaiveDate::from_ymd_opt(time.year().into(), time.month().into(), time.day().into())
+        .and_then(|x| {
+            x.and_hms_opt(
+                time.hour().into(),
+                time.minute().into(),
+                time.second().into(),
+            )
+        })
+        .map_or(0, |x| x.and_utc().timestamp().max(0) as u64)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    /// Writes the raw names, bypassing the path checks of the tar crate
+    fn tar_archive(dir: &TempDir, entries: &[(&str, tar::EntryType, &str)]) -> Archive {
+        let path = dir.join("archive.tar.gz");
+        let encoder = flate2::write::GzEncoder::new(
+            File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut builder = tar::Builder::new(encoder);
+
+        for (name, entry_type, contents) in entries {
+            let mut header = tar::Header::new_old();
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_entry_type(*entry_type);
+            header.set_mode(0o644);
+            let data = match entry_type {
+                tar::EntryType::Regular => contents.as_bytes(),
+                _ => {
+                    header.as_old_mut().linkname[..contents.len()]
+                        .copy_from_slice(contents.as_bytes());
+                    &[]
+                }
+            };
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            builder.append(&header, data).unwrap();
+        }
+
+        builder.into_inner().unwrap().finish().unwrap();
+        Archive::new(path)
+    }
+
+    /// Extracts into `dir/dest`, returning the path of the unsafe entry
+    fn extract(dir: &TempDir, entries: &[(&str, tar::EntryType, &str)]) -> Result<(), String> {
+        match tar_archive(dir, entries).extract(&dir.join("dest")) {
+            Ok(()) => Ok(()),
+            Err(CompressError::UnsafePath(path)) => Err(path),
+            Err(error) => panic!("{error}"),
+        }
+    }
+
+    use tar::EntryType::{Regular, Symlink};
+
+    #[test]
+    fn extracts_contained_links() {
+        let dir = TempDir::new();
+        extract(
+            &dir,
+            &[
+                ("lib/app.so", Regular, "library"),
+                ("bin/app.so", Symlink, "../lib/app.so"),
+                ("current", Symlink, "./lib"),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dir.join("dest/bin/app.so")).unwrap(),
+            "library"
+        );
+        assert_eq!(
+            std::fs::read_link(dir.join("dest/current")).unwrap(),
+            Path::new("./lib")
+        );
+    }
+
+    #[test]
+    fn rejects_absolute_paths() {
+        assert_eq!(
+            extract(&TempDir::new(), &[("/tmp/evil", Regular, "x")]).unwrap_err(),
+            "/tmp/evil"
+        );
+        assert_eq!(
+            extract(&TempDir::new(), &[("etc", Symlink, "/etc")]).unwrap_err(),
+            "etc"
+        );
+    }
+
+    #[test]
+    fn rejects_parent_paths() {
+        assert_eq!(
+            extract(&TempDir::new(), &[("a/../../evil", Regular, "x")]).unwrap_err(),
+            "a/../../evil"
+        );
+        assert_eq!(
+            extract(&TempDir::new(), &[("a/up", Symlink, "../..")]).unwrap_err(),
+            "a/up"
+        );
+    }
+
+    #[test]
+    fn rejects_chained_links() {
+        let dir = TempDir::new();
+        let error = extract(
+            &dir,
+            &[
+                ("s", Symlink, "."),
+                ("a", Symlink, "s/.."),
+                ("a/evil", Regular, "x"),
+            ],
+        )
+        .unwrap_err();
+
+        assert_eq!(error, "a");
+        assert!(!dir.join("evil").exists());
+    }
+
+    #[test]
+    fn rejects_links_through_extracted_links() {
+        assert_eq!(
+            extract(
+                &TempDir::new(),
+                &[("lib/up", Symlink, ".."), ("lib/up/out", Symlink, "../x")],
+            )
+            .unwrap_err(),
+            "lib/up/out"
+        );
+    }
+
+    #[test]
+    fn rejects_links_resolved_by_later_links() {
+        let dir = TempDir::new();
+        let error = extract(&dir, &[("a", Symlink, "b/.."), ("b", Symlink, ".")]).unwrap_err();
+
+        assert_eq!(error, "a");
+        assert!(std::fs::symlink_metadata(dir.join("dest/a")).is_err());
+    }
+
+    #[test]
+    fn does_not_write_through_links() {
+        let dir = TempDir::new();
+        extract(&dir, &[("a", Symlink, "x"), ("a", Regular, "data")]).unwrap();
+
+        assert!(!dir.join("dest/x").exists());
+        assert_eq!(std::fs::read_to_string(dir.join("dest/a")).unwrap(), "data");
+    }
+
+    #[cfg(feature = "targz-zip")]
+    fn extract_zip(dir: &TempDir, entries: &[(&str, tar::EntryType, &str)]) -> Result<(), String> {
+        use std::io::Write;
+        use zip::write::SimpleFileOptions;
+
+        let path = dir.join("archive.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        for (name, entry_type, contents) in entries {
+            match entry_type {
+                tar::EntryType::Regular => {
+                    zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+                    zip.write_all(contents.as_bytes()).unwrap();
+                }
+                _ => zip
+                    .add_symlink(*name, *contents, SimpleFileOptions::default())
+                    .unwrap(),
+            }
+        }
+        zip.finish().unwrap();
+
+        match Archive::new(&path).extract(&dir.join("dest")) {
+            Ok(()) => Ok(()),
+            Err(CompressError::UnsafePath(path)) => Err(path),
+            Err(error) => panic!("{error}"),
+        }
+    }
+
+    #[cfg(feature = "targz-zip")]
+    #[test]
+    fn rejects_chained_links_in_zip() {
+        let dir = TempDir::new();
+        let error = extract_zip(&dir, &[("s", Symlink, "."), ("a", Symlink, "s/..")]).unwrap_err();
+        assert_eq!(error, "a");
+    }
+
+    #[cfg(feature = "targz-zip")]
+    #[test]
+    fn rejects_links_resolved_by_later_links_in_zip() {
+        let dir = TempDir::new();
+        let error = extract_zip(
+            &dir,
+            &[
+                ("a", Symlink, "b/../x"),
+                ("b", Symlink, "."),
+                ("./a", Regular, "x"),
+            ],
+        )
+        .unwrap_err();
+
+        assert_eq!(error, "a");
+        assert!(!dir.join("x").exists());
+    }
+
+    #[cfg(feature = "targz-zip")]
+    #[test]
+    fn does_not_write_through_links_in_zip() {
+        let dir = TempDir::new();
+        extract_zip(&dir, &[("a", Symlink, "x"), ("./a", Regular, "data")]).unwrap();
+
+        assert!(!dir.join("dest/x").exists());
+        assert_eq!(std::fs::read_to_string(dir.join("dest/a")).unwrap(), "data");
+    }
+}
@@ -0,0 +1,304 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use super::{ArchiveFormat, CompressError};
+use crate::git::{last_commit_date, source_date_epoch};
+
+/// Timestamp used by reproducible archives: [`SOURCE_DATE_EPOCH`](crate::git::SOURCE_DATE_EPOCH)
+/// if set, the date of the last commit otherwise
+pub fn reproducible_mtime() -> Result<u64, CompressError> {
+    let date = match source_date_epoch()? {
+        Some(date) => date,
+        None => last_commit_date()?,
+    };
+    Ok(date.timestamp().max(0) as u64)
+}
+
+/// `0o755` for directories and executables, `0o644` for everything else
+fn normalized_mode(mode: u32, is_dir: bool) -> u32 {
+    if is_dir || mode & 0o111 != 0 {
+        0o755
+    } else {
+        0o644
+    }
+}
+
+/// Reproducible tar header: normalized mode and owner, mtime clamped to `max_mtime`
+fn normalize(header: &mut tar::Header, max_mtime: Option<u64>) -> Result<(), CompressError> {
+    if let Some(max_mtime) = max_mtime {
+        let mode = header.mode()?;
+        let is_dir = header.entry_type().is_dir();
+        header.set_mode(normalized_mode(mode, is_dir));
+        header.set_mtime(header.mtime()?.min(max_mtime));
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_username("")?;
+        header.set_groupname("")?;
+    }
+    Ok(())
+}
+
+/// Compression stream below the tar builder
+pub(crate) enum Encoder {
+    Gz(flate2::write::GzEncoder<File>),
+    #[cfg(feature = "targz-xz")]
+    Xz(xz2::write::XzEncoder<File>),
+    #[cfg(feature = "targz-zstd")]
+    Zst(zstd::Encoder<'static, File>),
+    #[cfg(feature = "targz-bzip2")]
+    Bz2(bzip2::write::BzEncoder<File>),
+}
+
+impl Encoder {
+    fn new(format: ArchiveFormat, file: File, level: Option<u32>) -> Result<Self, CompressError> {
+        let range = format.compression_levels();
+        let level = level.map(|x| x.clamp(*range.start(), *range.end()));
+
+        match format {
+            // the gzip header time is always zeroed, the entries carry the mtimes
+            ArchiveFormat::TarGz => Ok(Self::Gz(flate2::GzBuilder::new().mtime(0).write(
+                file,
+                level.map_or_else(flate2::Compression::default, flate2::Compression::new),
+            ))),
+            #[cfg(feature = "targz-xz")]
+            ArchiveFormat::TarXz => Ok(Self::Xz(xz2::write::XzEncoder::new(
+                file,
+                level.unwrap_or(6),
+            ))),
+            #[cfg(feature = "targz-zstd")]
+            ArchiveFormat::TarZst => Ok(Self::Zst(zstd::Encoder::new(
+                file,
+                level.map_or(zstd::DEFAULT_COMPRESSION_LEVEL, |x| x as i32),
+            )?)),
+            #[cfg(feature = "targz-bzip2")]
+            ArchiveFormat::TarBz2 => Ok(Self::Bz2(bzip2::write::BzEncoder::new(
+                file,
+                level.map_or_else(bzip2::Compression::default, bzip2::Compression::new),
+            ))),
+            _ => Err(CompressError::UnsupportedFormat(format)),
+        }
+    }
+
+    fn finish(self) -> std::io::Result<File> {
+        match self {
+            Self::Gz(x) => x.finish(),
+            #[cfg(feature = "targz-xz")]
+            Self::Xz(x) => x.finish(),
+            #[cfg(feature = "targz-zstd")]
+            Self::Zst(x) => x.finish(),
+            #[cfg(feature = "targz-bzip2")]
+            Self::Bz2(x) => x.finish(),
+        }
+    }
+}
+
+impl Write for Encoder {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Gz(x) => x.write(buf),
+            #[cfg(feature = "targz-xz")]
+            Self::Xz(x) => x.write(buf),
+            #[cfg(feature = "targz-zstd")]
+            Self::Zst(x) => x.write(buf),
+            #[cfg(feature = "targz-bzip2")]
+            Self::Bz2(x) => x.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Gz(x) => x.flush(),
+            #[cfg(feature = "targz-xz")]
+            Self::Xz(x) => x.flush(),
+            #[cfg(feature = "targz-zstd")]
+            Self::Zst(x) => x.flush(),
+            #[cfg(feature = "targz-bzip2")]
+            Self::Bz2(x) => x.flush(),
+        }
+    }
+}
+
+enum Inner {
+    Tar(tar::Builder<Encoder>),
+    #[cfg(feature = "targz-zip")]
+    Zip(Box<zip::ZipWriter<File>>, zip::write::SimpleFileOptions),
+}
+
+/// Archive builder rejecting duplicate paths, optionally writing reproducible entries
+pub(crate) struct ArchiveWriter {
+    inner: Inner,
+    /// Upper bound for the mtimes, set in the reproducible mode
+    mtime: Option<u64>,
+    archived: HashSet<String>,
+}
+
+impl ArchiveWriter {
+    pub fn create(
+        path: &Path,
+        format: ArchiveFormat,
+        level: Option<u32>,
+        mtime: Option<u64>,
+    ) -> Result<Self, CompressError> {
+        if !format.is_supported() {
+            return Err(CompressError::UnsupportedFormat(format));
+        }
+
+        let file = File::create(path)?;
+        let inner = match format {
+            #[cfg(feature = "targz-zip")]
+            ArchiveFormat::Zip => {
+                let range = format.compression_levels();
+                let options = zip::write::SimpleFileOptions::default()
+                    .compression_method(zip::CompressionMethod::Deflated)
+                    .compression_level(level.map(|x| x.clamp(*range.start(), *range.end()) as i64));
+                Inner::Zip(Box::new(zip::ZipWriter::new(file)), options)
+            }
+            _ => Inner::Tar(tar::Builder::new(Encoder::new(format, file, level)?)),
+        };
+
+        Ok(Self {
+            inner,
+            mtime,
+            archived: HashSet::new(),
+        })
+    }
+
+    fn claim(&mut self, name: &str) -> Result<(), CompressError> {
+        if self.archived.insert(name.to_string()) {
+            Ok(())
+        } else {
+            Err(CompressError::DuplicatePath(name.to_string()))
+        }
+    }
+
+    /// Append the file at `src` as `name`, with its own mode unless `mode` is given. Symlinks are
+    /// followed
+    pub fn append_path(
+        &mut self,
+        src: &Path,
+        name: &str,
+        mode: Option<u32>,
+    ) -> Result<(), CompressError> {
+        self.claim(name)?;
+
+        let file = File::open(src)?;
+        let metadata = file.metadata()?;
+
+        match &mut self.inner {
+            Inner::Tar(builder) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_metadata(&metadata);
+                normalize(&mut header, self.mtime)?;
+                if let Some(mode) = mode {
+                    header.set_mode(mode);
+                }
+                builder.append_data(&mut header, name, file)?;
+            }
+            #[cfg(feature = "targz-zip")]
+            Inner::Zip(zip, options) => {
+                let mtime = metadata
+                    .modified()?
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |x| x.as_secs());
+                let mtime = self.mtime.map_or(mtime, |x| x.min(mtime));
+                let mode = mode.unwrap_or_else(|| match self.mtime {
+                    Some(_) => normalized_mode(file_mode(&metadata), false),
+                    None => file_mode(&metadata),
+                });
+
+                let options = options
+                    .unix_permissions(mode)
+                    .last_modified_time(zip_time(mtime));
+                zip.start_file(name, options)?;
+                std::io::copy(&mut &file, zip)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Append a file with the given contents. Its mtime is the current time, or the clamping
+    /// time in the reproducible mode
+    pub fn append_data(
+        &mut self,
+        name: &str,
+        mode: u32,
+        contents: &[u8],
+    ) -> Result<(), CompressError> {
+        self.claim(name)?;
+
+        let mtime = self.mtime.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |x| x.as_secs())
+        });
+
+        match &mut self.inner {
+            Inner::Tar(builder) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_entry_type(tar::EntryType::Regular);
+                header.set_size(contents.len() as u64);
+                header.set_mode(mode);
+                header.set_mtime(mtime);
+                header.set_uid(0);
+                header.set_gid(0);
+                builder.append_data(&mut header, name, contents)?;
+            }
+            #[cfg(feature = "targz-zip")]
+            Inner::Zip(zip, options) => {
+                let options = options
+                    .unix_permissions(mode)
+                    .last_modified_time(zip_time(mtime));
+                zip.start_file(name, options)?;
+                zip.write_all(contents)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<(), CompressError> {
+        match self.inner {
+            Inner::Tar(builder) => {
+                builder.into_inner()?.finish()?;
+            }
+            #[cfg(feature = "targz-zip")]
+            Inner::Zip(zip, _) => {
+                zip.finish()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(feature = "targz-zip", unix))]
+fn file_mode(metadata: &std::fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(all(feature = "targz-zip", not(unix)))]
+fn file_mode(_metadata: &std::fs::Metadata) -> u32 {
+    0o644
+}
+
+/// Zip timestamps are stored without a time zone, UTC is used. Dates outside of the zip range
+/// fall back to 1980-01-01
+#[cfg(feature = "targz-zip")]
+fn zip_time(mtime: u64) -> zip::DateTime {
+    use chrono::{Datelike, Timelike};
+
+    chrono::DateTime::from_timestamp(mtime as i64, 0)
+        .and_then(|x| {
+            zip::DateTime::from_date_and_time(
+                x.year().try_into().ok()?,
+                x.month() as u8,
+                x.day() as u8,
+                x.hour() as u8,
+                x.minute() as u8,
+                x.second() as u8,
+            )
+            .ok()
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,424 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use xshell::{cmd, Shell};
+
+use crate::cargo::{get_project_root, ProjectRootError};
+use crate::checksums::{
+    Checksum, ChecksumFile, ChecksumFormat, Filter, HashAlgorithm, PathChecksum,
+};
+
+/// Digest recorded for inputs that do not exist (missing paths, unset variables)
+const ABSENT: &str = "absent";
+
+#[derive(Debug, thiserror::Error)]
+pub enum TaskCacheError {
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+
+    #[error(transparent)]
+    XShellError(#[from] xshell::Error),
+
+    #[error(transparent)]
+    SerdeError(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    ProjectRootError(#[from] ProjectRootError),
+}
+
+#[derive(Debug, Clone)]
+enum Input {
+    Path(PathBuf),
+    Glob { root: PathBuf, glob: String },
+    Env(String),
+    Tool { program: String, args: Vec<String> },
+    Value { key: String, value: String },
+}
+
+impl Input {
+    fn key(&self) -> String {
+        match self {
+            Self::Path(path) => format!("path:{}", path.display()),
+            Self::Glob { root, glob } => format!("glob:{}:{glob}", root.display()),
+            Self::Env(name) => format!("env:{name}"),
+            Self::Tool { program, args } => format!("tool:{program} {}", args.join(" ")),
+            Self::Value { key, .. } => format!("value:{key}"),
+        }
+    }
+
+    /// Paths and globs skip `cache_dir`, so the cache files are never inputs themselves
+    fn digest(&self, cache_dir: &Path) -> Result<String, TaskCacheError> {
+        let sha256 = |data: &[u8]| {
+            Checksum::from_bytes(HashAlgorithm::Sha256, &HashAlgorithm::Sha256.digest(data))
+                .string()
+        };
+
+        Ok(match self {
+            Self::Path(path) if !path.exists() => ABSENT.to_string(),
+            Self::Path(path) => {
+                let filter = without_dir(Filter::new().git_ignore(), path, cache_dir);
+                path.calculate_tree_filtered(HashAlgorithm::Sha256, &filter)?
+                    .string()
+            }
+            Self::Glob { root, glob } => {
+                let filter = without_dir(Filter::new().git_ignore().include(glob), root, cache_dir);
+                let entries =
+                    root.calculate_entries_recursive_filtered(HashAlgorithm::Sha256, &filter)?;
+                sha256(
+                    ChecksumFile::new(entries)
+                        .format(ChecksumFormat::Gnu)
+                        .as_bytes(),
+                )
+            }
+            // values are hashed, so secrets do not end up in the cache file
+            Self::Env(name) => match std::env::var_os(name) {
+                Some(value) => sha256(value.as_encoded_bytes()),
+                None => ABSENT.to_string(),
+            },
+            Self::Tool { program, args } => {
+                let sh = Shell::new()?;
+                sha256(cmd!(sh, "{program} {args...}").read()?.as_bytes())
+            }
+            Self::Value { value, .. } => sha256(value.as_bytes()),
+        })
+    }
+}
+
+/// Skip `dir` when it is below `root`
+fn without_dir(filter: Filter, root: &Path, dir: &Path) -> Filter {
+    let (Ok(canonical_root), Ok(dir)) = (root.canonicalize(), dir.canonicalize()) else {
+        return filter;
+    };
+    match dir.strip_prefix(&canonical_root) {
+        Ok(relative) if !relative.as_os_str().is_empty() => {
+            // walked paths start with `root` as given
+            let skipped = root.join(relative);
+            filter.predicate(move |path| path != skipped)
+        }
+        _ => filter,
+    }
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct CacheFile {
+    inputs: BTreeMap<String, String>,
+}
+
+/// Result of comparing the inputs with the last successful run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheStatus {
+    UpToDate,
+    /// No successful run was recorded yet
+    Missing,
+    /// Inputs that were added, removed or changed since the last successful run
+    Changed(Vec<String>),
+}
+
+/// Skips xtask steps whose inputs did not change since their last successful run
+///
+/// Input digests are stored in `target/xtask-cache/<name>.json` (honoring `CARGO_TARGET_DIR`)
+///
+/// ```ignore
+/// let check = TaskCache::new("docs")
+///     .with_path("docs")
+///     .with_glob("src", "*.rs")
+///     .with_env("RUSTDOCFLAGS")
+///     .with_tool_version("rustdoc", ["--version"])
+///     .check()?;
+///
+/// if !check.is_up_to_date() {
+///     // build the docs
+///     check.save()?;
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TaskCache {
+    name: String,
+    cache_dir: Option<PathBuf>,
+    inputs: Vec<Input>,
+}
+
+impl TaskCache {
+    pub fn new<S>(name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            cache_dir: None,
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Directory for the cache files instead of `target/xtask-cache`
+    pub fn with_cache_dir<P>(&mut self, dir: P) -> &mut Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    /// File or directory. Directories are hashed with paths, exec bits and symlinks. Files
+    /// ignored by git and the cache directory are skipped
+    pub fn with_path<P>(&mut self, path: P) -> &mut Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.inputs.push(Input::Path(path.into()));
+        self
+    }
+
+    /// Files below `root` matching the glob. Files ignored by git and the cache directory are
+    /// skipped
+    pub fn with_glob<P, S>(&mut self, root: P, glob: S) -> &mut Self
+    where
+        P: Into<PathBuf>,
+        S: Into<String>,
+    {
+        self.inputs.push(Input::Glob {
+            root: root.into(),
+            glob: glob.into(),
+        });
+        self
+    }
+
+    pub fn with_env<S>(&mut self, name: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.inputs.push(Input::Env(name.into()));
+        self
+    }
+
+    /// Output of the command, e.g. `rustc --version`
+    pub fn with_tool_version<S, I, A>(&mut self, program: S, args: I) -> &mut Self
+    where
+        S: Into<String>,
+        I: IntoIterator<Item = A>,
+        A: Into<String>,
+    {
+        self.inputs.push(Input::Tool {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+        });
+        self
+    }
+
+    /// Any other value the task depends on, e.g. a target triple or a profile
+    pub fn with_value<K, V>(&mut self, key: K, value: V) -> &mut Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.inputs.push(Input::Value {
+            key: key.into(),
+            value: value.into(),
+        });
+        self
+    }
+
+    fn cache_file(&self) -> Result<PathBuf, TaskCacheError> {
+        let dir = match &self.cache_dir {
+            Some(dir) => dir.clone(),
+            None => match std::env::var_os("CARGO_TARGET_DIR") {
+                Some(target) => PathBuf::from(target).join("xtask-cache"),
+                None => get_project_root()?.join("target").join("xtask-cache"),
+            },
+        };
+        Ok(dir.join(format!("{}.json", self.name)))
+    }
+
+    /// Hash the inputs and compare them with the last successful run
+    pub fn check(&self) -> Result<CacheCheck, TaskCacheError> {
+        let cache_file = self.cache_file()?;
+        let cache_dir = cache_file.parent().unwrap_or(Path::new("."));
+        let mut inputs = BTreeMap::new();
+        for input in &self.inputs {
+            inputs.insert(input.key(), input.digest(cache_dir)?);
+        }
+
+        let status = match read_cache(&cache_file)? {
+            None => CacheStatus::Missing,
+            Some(previous) => {
+                let mut changed = inputs
+                    .iter()
+                    .filter(|(key, digest)| previous.inputs.get(*key) != Some(*digest))
+                    .map(|(key, _)| key.clone())
+                    .collect::<Vec<_>>();
+                changed.extend(
+                    previous
+                        .inputs
+                        .keys()
+                        .filter(|key| !inputs.contains_key(*key))
+                        .cloned(),
+                );
+
+                if changed.is_empty() {
+                    CacheStatus::UpToDate
+                } else {
+                    CacheStatus::Changed(changed)
+                }
+            }
+        };
+
+        Ok(CacheCheck {
+            cache_file,
+            inputs,
+            status,
+        })
+    }
+
+    /// Run `task` unless the inputs are up to date. Returns whether it was run
+    ///
+    /// The digests taken before the run are stored only if the task succeeds
+    pub fn run_if_changed<F, E>(&self, task: F) -> Result<bool, E>
+    where
+        F: FnOnce(&CacheStatus) -> Result<(), E>,
+        E: From<TaskCacheError>,
+    {
+        let check = self.check()?;
+        if check.is_up_to_date() {
+            return Ok(false);
+        }
+
+        task(&check.status)?;
+        check.save()?;
+        Ok(true)
+    }
+
+    /// Forget the last successful run
+    pub fn invalidate(&self) -> Result<(), TaskCacheError> {
+        match std::fs::remove_file(self.cache_file()?) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn read_cache(path: &Path) -> Result<Option<CacheFile>, TaskCacheError> {
+    match std::fs::read_to_string(path) {
+        // an unreadable cache is treated as a missing one
+        Ok(contents) => Ok(serde_json::from_str(&contents).ok()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Input digests taken by [`TaskCache::check`]
+#[derive(Debug, Clone)]
+pub struct CacheCheck {
+    cache_file: PathBuf,
+    inputs: BTreeMap<String, String>,
+    pub status: CacheStatus,
+}
+
+impl CacheCheck {
+    pub fn is_up_to_date(&self) -> bool {
+        self.status == CacheStatus::UpToDate
+    }
+
+    /// Inputs that changed since the last successful run. Empty when there was no run yet
+    pub fn changed(&self) -> &[String] {
+        match &self.status {
+            CacheStatus::Changed(changed) => changed,
+            _ => &[],
+        }
+    }
+
+    /// Record the digests as the last successful run
+    pub fn save(&self) -> Result<(), TaskCacheError> {
+        if let Some(dir) = self.cache_file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let cache = CacheFile {
+            inputs: self.inputs.clone(),
+        };
+        std::fs::write(&self.cache_file, serde_json::to_string_pretty(&cache)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    /// Task hashing the whole directory, with its cache below it
+    fn task(dir: &TempDir) -> TaskCache {
+        // as after any cargo build. Only the cache directory is skipped, not its parents
+        std::fs::create_dir_all(dir.join("target")).unwrap();
+        let mut task = TaskCache::new("build");
+        task.with_cache_dir(dir.join("target/xtask-cache"))
+            .with_path(dir.path())
+            .with_glob(dir.path(), "*.json")
+            .with_value("profile", "release");
+        task
+    }
+
+    #[test]
+    fn is_up_to_date_after_save() {
+        let dir = TempDir::new();
+        dir.write("src/main.rs", "fn main() {}");
+        dir.write("config.json", "{}");
+        let task = task(&dir);
+
+        let check = task.check().unwrap();
+        assert_eq!(check.status, CacheStatus::Missing);
+        assert!(check.changed().is_empty());
+        check.save().unwrap();
+        assert!(dir.join("target/xtask-cache/build.json").is_file());
+
+        assert_eq!(task.check().unwrap().status, CacheStatus::UpToDate);
+        task.invalidate().unwrap();
+        assert_eq!(task.check().unwrap().status, CacheStatus::Missing);
+    }
+
+    #[test]
+    fn reports_changed_inputs() {
+        let dir = TempDir::new();
+        dir.write("src/main.rs", "fn main() {}");
+        task(&dir).check().unwrap().save().unwrap();
+
+        dir.write("src/main.rs", "fn main() { todo!() }");
+        let check = task(&dir).check().unwrap();
+        assert_eq!(check.changed(), [format!("path:{}", dir.path().display())]);
+
+        // path removed, value changed, target added
+        let mut changed = TaskCache::new("build");
+        changed
+            .with_cache_dir(dir.join("target/xtask-cache"))
+            .with_glob(dir.path(), "*.json")
+            .with_value("profile", "debug")
+            .with_value("target", "x86_64-unknown-linux-gnu");
+        assert_eq!(
+            changed.check().unwrap().status,
+            CacheStatus::Changed(vec![
+                "value:profile".to_string(),
+                "value:target".to_string(),
+                format!("path:{}", dir.path().display()),
+            ])
+        );
+    }
+
+    #[test]
+    fn saves_only_successful_runs() {
+        let dir = TempDir::new();
+        dir.write("src/main.rs", "fn main() {}");
+        let task = task(&dir);
+
+        let failed = task.run_if_changed(|status| {
+            assert_eq!(status, &CacheStatus::Missing);
+            Err(TaskCacheError::IoError(std::io::ErrorKind::Other.into()))
+        });
+        assert!(failed.is_err());
+        assert_eq!(task.check().unwrap().status, CacheStatus::Missing);
+
+        assert!(task
+            .run_if_changed(|_| Ok::<_, TaskCacheError>(()))
+            .unwrap());
+        let run = task
+            .run_if_changed(|_| -> Result<(), TaskCacheError> { panic!("inputs did not change") });
+        assert!(!run.unwrap());
+    }
+}